@@ -0,0 +1,108 @@
+//! Benchmarks for the `CloneByteBuffer` copy paths.
+//!
+//! Run with `cargo bench`. Absolute times depend on the machine, so compare
+//! runs on the same box, e.g. `cargo bench -- --save-baseline before` on the old
+//! commit and `cargo bench -- --baseline before` on the new one.
+//!
+//! `put_buf`/`get_buf` copy with `copy_from_slice`, so the bulk numbers are
+//! memcpy speed. `slice()` only bumps the `Rc` on the shared backing store and
+//! doesn't depend on capacity.
+
+use bytebuffers::buffer::buffer::IBuffer;
+use bytebuffers::buffer::clone_bytebuffer::CloneByteBuffer;
+use criterion::{black_box, criterion_group, criterion_main, Criterion, Throughput};
+
+const INTS: i32 = 1024;
+const BULK: i32 = 64 * 1024;
+
+fn bench_i32(c: &mut Criterion) {
+    let mut group = c.benchmark_group("i32");
+    group.throughput(Throughput::Bytes(INTS as u64 * 4));
+    let mut buffer = CloneByteBuffer::new2(INTS * 4, INTS * 4);
+    group.bench_function("put_get_1024", |b| {
+        b.iter(|| {
+            buffer.clear();
+            for i in 0..INTS {
+                buffer.put_i32(black_box(i));
+            }
+            buffer.flip();
+            let mut sum = 0i32;
+            for _ in 0..INTS {
+                sum = sum.wrapping_add(buffer.get_i32());
+            }
+            sum
+        })
+    });
+    // the fallible, budget-checked path
+    group.bench_function("try_put_get_1024", |b| {
+        b.iter(|| {
+            buffer.clear();
+            for i in 0..INTS {
+                buffer.try_put_i32(black_box(i)).unwrap();
+            }
+            buffer.flip();
+            let mut sum = 0i32;
+            for _ in 0..INTS {
+                sum = sum.wrapping_add(buffer.try_get_i32().unwrap());
+            }
+            sum
+        })
+    });
+    group.finish();
+}
+
+fn bench_bulk(c: &mut Criterion) {
+    let mut group = c.benchmark_group("bulk");
+    group.throughput(Throughput::Bytes(BULK as u64));
+    let mut buffer = CloneByteBuffer::new2(BULK, BULK);
+    let mut data = vec![0xa5u8; BULK as usize];
+    group.bench_function("put_buf_64k", |b| {
+        b.iter(|| {
+            buffer.clear();
+            buffer.put_buf(black_box(&mut data), 0, BULK);
+        })
+    });
+    group.bench_function("get_buf_64k", |b| {
+        b.iter(|| {
+            buffer.clear();
+            buffer.get_buf(black_box(&mut data), 0, BULK);
+        })
+    });
+    group.finish();
+}
+
+fn bench_get(c: &mut Criterion) {
+    let mut group = c.benchmark_group("get");
+    group.throughput(Throughput::Bytes(4096));
+    let mut buffer = CloneByteBuffer::new2(4096, 4096);
+    group.bench_function("get_4k", |b| {
+        b.iter(|| {
+            buffer.clear();
+            let mut sum = 0u8;
+            while buffer.has_remaining() {
+                sum = sum.wrapping_add(buffer.get());
+            }
+            sum
+        })
+    });
+    group.bench_function("get_unchecked_4k", |b| {
+        b.iter(|| {
+            buffer.clear();
+            let mut sum = 0u8;
+            while buffer.has_remaining() {
+                sum = sum.wrapping_add(unsafe { buffer.get_unchecked() });
+            }
+            sum
+        })
+    });
+    group.finish();
+}
+
+fn bench_slice(c: &mut Criterion) {
+    let mut buffer = CloneByteBuffer::new2(4096, 4096);
+    buffer.position_(16);
+    c.bench_function("slice/slice", |b| b.iter(|| black_box(&buffer).slice()));
+}
+
+criterion_group!(benches, bench_i32, bench_bulk, bench_get, bench_slice);
+criterion_main!(benches);
@@ -0,0 +1,185 @@
+//! Drive a `CloneByteBuffer` over arbitrary bytes with a random sequence of reads
+//! and cursor moves. The only panics allowed are the documented ones (underflow,
+//! illegal argument, ...); anything else, e.g. a slice index out of range in the
+//! index math, is a crash.
+//!
+//! Run with `cargo +nightly fuzz run clone_bytebuffer` from the repo root.
+#![no_main]
+
+use std::panic::{self, AssertUnwindSafe, PanicHookInfo};
+use std::sync::Once;
+
+use arbitrary::Arbitrary;
+use bytebuffers::buffer::buffer::IBuffer;
+use bytebuffers::buffer::byte_order::ByteOrder;
+use bytebuffers::buffer::clone_bytebuffer::CloneByteBuffer;
+use libfuzzer_sys::fuzz_target;
+
+#[derive(Arbitrary, Debug)]
+struct Input {
+    data: Vec<u8>,
+    position: i32,
+    limit: i32,
+    little_endian: bool,
+    ops: Vec<Op>,
+}
+
+#[derive(Arbitrary, Debug)]
+enum Op {
+    Get,
+    GetI(i32),
+    GetI16,
+    GetI32,
+    GetI64,
+    GetF64,
+    GetU128,
+    GetVarint,
+    GetBytesPrefixed,
+    GetCstr,
+    GetUtf8Chunk(i32),
+    Skip(i32),
+    Find(u8),
+    GetUntil(u8),
+    DrainChunk(i32),
+    Position(i32),
+    Limit(i32),
+    Mark,
+    Reset,
+    Flip,
+    Rewind,
+    Compact,
+    Slice,
+}
+
+/// Prefixes of the panic messages the accessors document.
+const DOCUMENTED: &[&str] = &[
+    "buffer under flow",
+    "buffer over flow",
+    "illegal argument",
+    "index out of bound",
+    "invalid mark",
+    "varint is longer than 10 bytes",
+];
+
+fn is_documented(msg: &str) -> bool {
+    DOCUMENTED.iter().any(|d| msg.starts_with(d))
+}
+
+fn panic_message(info: &PanicHookInfo<'_>) -> String {
+    let payload = info.payload();
+    payload
+        .downcast_ref::<&str>()
+        .map(|s| s.to_string())
+        .or_else(|| payload.downcast_ref::<String>().cloned())
+        .unwrap_or_default()
+}
+
+/// libfuzzer-sys aborts on every panic; only let the undocumented ones through to it.
+fn install_hook() {
+    static HOOK: Once = Once::new();
+    HOOK.call_once(|| {
+        let fuzzer_hook = panic::take_hook();
+        panic::set_hook(Box::new(move |info| {
+            if !is_documented(&panic_message(info)) {
+                fuzzer_hook(info);
+            }
+        }));
+    });
+}
+
+fn apply(buffer: &mut CloneByteBuffer, op: &Op) {
+    match *op {
+        Op::Get => {
+            buffer.get();
+        }
+        Op::GetI(i) => {
+            buffer.get_i(i);
+        }
+        Op::GetI16 => {
+            buffer.get_i16();
+        }
+        Op::GetI32 => {
+            buffer.get_i32();
+        }
+        Op::GetI64 => {
+            buffer.get_i64();
+        }
+        Op::GetF64 => {
+            let _ = buffer.try_get_f64();
+        }
+        Op::GetU128 => {
+            buffer.get_u128();
+        }
+        Op::GetVarint => {
+            buffer.get_varint();
+        }
+        Op::GetBytesPrefixed => {
+            buffer.get_bytes_prefixed();
+        }
+        Op::GetCstr => {
+            let _ = buffer.get_cstr();
+        }
+        Op::GetUtf8Chunk(n) => {
+            buffer.get_utf8_chunk(n);
+        }
+        Op::Skip(n) => {
+            buffer.skip(n);
+        }
+        Op::Find(b) => {
+            if let Some(i) = buffer.find(b) {
+                assert!(i >= buffer.position() && i < buffer.limit());
+            }
+        }
+        Op::GetUntil(b) => {
+            buffer.get_until(b);
+        }
+        Op::DrainChunk(n) => {
+            buffer.drain_chunk(n);
+        }
+        Op::Position(p) => {
+            buffer.position_(p);
+        }
+        Op::Limit(l) => {
+            buffer.limit_(l);
+        }
+        Op::Mark => {
+            buffer.mark_();
+        }
+        Op::Reset => {
+            buffer.reset();
+        }
+        Op::Flip => {
+            buffer.flip();
+        }
+        Op::Rewind => {
+            buffer.rewind();
+        }
+        Op::Compact => {
+            buffer.compact();
+        }
+        Op::Slice => {
+            *buffer = buffer.slice();
+        }
+    }
+}
+
+fuzz_target!(|input: Input| {
+    install_hook();
+    let len = input.data.len() as i32;
+    let mut buffer = CloneByteBuffer::new3(&input.data, 0, len);
+    if buffer.set_state(input.position, input.limit, -1).is_err() {
+        return;
+    }
+    if input.little_endian {
+        buffer.order_(ByteOrder::LittleEndian);
+    }
+    for op in &input.ops {
+        let result = panic::catch_unwind(AssertUnwindSafe(|| apply(&mut buffer, op)));
+        if result.is_err() {
+            // a documented panic (the hook aborted on any other); the buffer may
+            // be half-updated, so start over
+            return;
+        }
+        assert!(buffer.position() <= buffer.limit() && buffer.limit() <= buffer.cap());
+    }
+});
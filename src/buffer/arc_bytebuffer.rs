@@ -1,12 +1,14 @@
-use std::cell::RefCell;
 use crate::buffer::buffer::{IBuffer, Buffer, ByteBuffer};
-use std::sync::Arc;
+use std::sync::{Arc, PoisonError, RwLock, RwLockReadGuard, RwLockWriteGuard};
 
+/// Thread-safe counterpart of `CloneByteBuffer`: `clone`, `slice` and `duplicate`
+/// give handles on the same bytes, which can be sent to other threads. Each
+/// handle has its own position, limit and mark.
 #[derive(Debug, Clone)]
 pub struct ArcByteBuffer {
     pub buffer: ByteBuffer,
-    // use RefCell for multiple slice buffer to share the same underlying buf
-    pub hb: Arc<Vec<u8>>,
+    // Arc<RwLock> so that slices and clones share the same underlying buf across threads
+    pub hb: Arc<RwLock<Vec<u8>>>,
     pub offset: i32,
 }
 
@@ -52,8 +54,9 @@ impl IBuffer for ArcByteBuffer {
         self
     }
 
-    fn truncate(&mut self) {
+    fn truncate(&mut self) -> &mut Self {
         self.buffer.clear();
+        self
     }
 
     fn flip(&mut self) -> &mut Self {
@@ -72,7 +75,11 @@ impl IBuffer for ArcByteBuffer {
     }
 
     fn get(&mut self) -> u8 {
-        self.buffer.get()
+        ArcByteBuffer::get(self)
+    }
+
+    fn put(&mut self, x: u8) {
+        ArcByteBuffer::put(self, x)
     }
 }
 
@@ -81,7 +88,7 @@ impl ArcByteBuffer {
         let buffer = ByteBuffer::new_(mark, pos, limit, cap);
         Self {
             buffer,
-            hb: Arc::new(buf.to_vec()),
+            hb: Arc::new(RwLock::new(buf.to_vec())),
             offset: 0,
         }
     }
@@ -94,7 +101,7 @@ impl ArcByteBuffer {
         }
         Self {
             buffer,
-            hb: Arc::new(buf.to_vec()),
+            hb: Arc::new(RwLock::new(buf.to_vec())),
             offset: 0,
         }
     }
@@ -103,19 +110,18 @@ impl ArcByteBuffer {
         let buffer = ByteBuffer::new_(-1, off, off + len, buf.len() as i32);
         Self {
             buffer: buffer,
-            hb: Arc::new(buf.to_vec()),
+            hb: Arc::new(RwLock::new(buf.to_vec())),
             offset: 0,
         }
     }
 
-    pub fn new_(buffer: ByteBuffer, hb: Arc<Vec<u8>>, offset: i32) -> Self {
+    pub fn new_(buffer: ByteBuffer, hb: Arc<RwLock<Vec<u8>>>, offset: i32) -> Self {
         Self {
             buffer, hb, offset
         }
     }
 
-    // todo: the result of RefCell clone is not expected: we want to change the slice and also change the parent buffer.
-    // but use clone() here will only change the slice hb buffer, not changing the parent buffer.
+    /// NIO `slice()`: a view of the remaining bytes, sharing storage with `self`.
     pub fn slice(&self) -> Self {
         let buffer = ByteBuffer::new_(-1, 0, self.buffer.remaining(), self.buffer.remaining());
         let ref_hb = &self.hb;
@@ -139,6 +145,16 @@ impl ArcByteBuffer {
         i + self.offset
     }
 
+    // The bytes are plain data, so a writer that panicked can't leave them
+    // inconsistent: ignore poisoning.
+    fn hb(&self) -> RwLockReadGuard<'_, Vec<u8>> {
+        self.hb.read().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    fn hb_mut(&self) -> RwLockWriteGuard<'_, Vec<u8>> {
+        self.hb.write().unwrap_or_else(PoisonError::into_inner)
+    }
+
     pub fn get(&mut self) -> u8 {
         let idx = self.buffer.buffer.next_get_index();
         self.get_idx_(idx)
@@ -151,8 +167,7 @@ impl ArcByteBuffer {
 
     fn get_idx_(&mut self, i: i32) -> u8 {
         let ix = self.ix(i) as usize;
-        let mut hb = Arc::clone(&self.hb);
-        hb[ix]
+        self.hb()[ix]
     }
 
     pub fn put(&mut self, x: u8) {
@@ -167,7 +182,7 @@ impl ArcByteBuffer {
 
     fn put_idx_(&mut self, x: u8, idx: i32) {
         let ix = self.ix(idx) as usize;
-        (*Arc::make_mut(&mut self.hb))[ix] = x;
+        self.hb_mut()[ix] = x;
     }
 
     // todo: batch copy?
@@ -185,7 +200,7 @@ impl ArcByteBuffer {
             panic!("buffer under flow")
         }
         let src_start = self.ix(self.position()) as usize;
-        let mut hb = Arc::clone(&self.hb);
+        let hb = self.hb();
         let mut idx = 0;
         for i in offset..offset + length {
             let id = i as usize;
@@ -193,6 +208,7 @@ impl ArcByteBuffer {
             idx += 1;
         }
         assert_eq!(idx, length as usize);
+        drop(hb);
         self.position_(self.position() + length);
         self
     }
@@ -206,12 +222,14 @@ impl ArcByteBuffer {
             panic!("buffer under flow")
         }
         let dst_start = self.ix(self.position()) as usize;
+        let mut hb = self.hb_mut();
         let mut idx = 0;
         for i in offset..offset + length {
             let id = i as usize;
-            (*Arc::make_mut(&mut self.hb))[dst_start +idx] = src[id];
+            hb[dst_start +idx] = src[id];
             idx += 1;
         }
+        drop(hb);
         // assert_eq!(idx+1, length as usize);
         self.position_(self.position() + length);
         self
@@ -233,16 +251,11 @@ impl ArcByteBuffer {
         let src_start = heap_buffer.ix(heap_buffer.position()) as usize;
         let dst_start = self.ix(self.position()) as usize;
 
-        // mutable buf vector
-        let mut src_hb = Arc::clone(&heap_buffer.hb);
+        // snapshot the source first: it may share the lock with `self`
+        let src_hb = heap_buffer.hb()[src_start..src_start + n].to_vec();
 
-        // copy from src_hb's src_start to hb's dst_start
-        let mut idx = 0;
-        for i in src_start..src_start + n {
-            let id = i as usize;
-            (*Arc::make_mut(&mut self.hb))[dst_start+idx] = src_hb[id];
-            idx += 1;
-        }
+        // copy from src_hb to hb's dst_start
+        self.hb_mut()[dst_start..dst_start + n].copy_from_slice(&src_hb);
         // update src and dst position
         heap_buffer.position_(heap_buffer.position() + n as i32);
         self.position_(self.position() + n as i32);
@@ -0,0 +1,112 @@
+use crate::buffer::buffer::IBuffer;
+use crate::buffer::clone_bytebuffer::CloneByteBuffer;
+
+/// Reads sub-byte fields MSB-first from a `CloneByteBuffer`. Whole bytes are
+/// consumed from the buffer as needed; the bit cursor inside the current byte
+/// is tracked here.
+#[derive(Debug, Clone)]
+pub struct BitReader {
+    pub buffer: CloneByteBuffer,
+    current: u8,
+    // unread bits left in `current`
+    bits_left: u32,
+}
+
+impl BitReader {
+    pub fn new(buffer: CloneByteBuffer) -> Self {
+        Self {
+            buffer,
+            current: 0,
+            bits_left: 0,
+        }
+    }
+
+    /// Read the next `n` bits (1..=64) as an unsigned value.
+    pub fn get_bits(&mut self, n: u32) -> u64 {
+        if n == 0 || n > 64 {
+            panic!("illegal argument!")
+        }
+        let mut value = 0u64;
+        let mut need = n;
+        while need > 0 {
+            if self.bits_left == 0 {
+                self.current = self.buffer.get();
+                self.bits_left = 8;
+            }
+            let take = need.min(self.bits_left);
+            let shift = self.bits_left - take;
+            let bits = (self.current as u64 >> shift) & ((1u64 << take) - 1);
+            value = (value << take) | bits;
+            self.bits_left -= take;
+            need -= take;
+        }
+        value
+    }
+
+    pub fn into_inner(self) -> CloneByteBuffer {
+        self.buffer
+    }
+}
+
+/// Writes sub-byte fields MSB-first into a `CloneByteBuffer`. When the limit is
+/// reached it is raised to the capacity, and when that is full the buffer grows.
+/// The partial byte is kept here until 8 bits are collected; call `finish` to
+/// zero-pad and flush it.
+#[derive(Debug, Clone)]
+pub struct BitWriter {
+    pub buffer: CloneByteBuffer,
+    current: u8,
+    // bits already collected in `current`
+    bits_used: u32,
+}
+
+impl BitWriter {
+    pub fn new(buffer: CloneByteBuffer) -> Self {
+        Self {
+            buffer,
+            current: 0,
+            bits_used: 0,
+        }
+    }
+
+    /// Write the low `n` bits (1..=64) of `value`.
+    pub fn put_bits(&mut self, value: u64, n: u32) {
+        if n == 0 || n > 64 {
+            panic!("illegal argument!")
+        }
+        for i in (0..n).rev() {
+            self.current = (self.current << 1) | ((value >> i) & 1) as u8;
+            self.bits_used += 1;
+            if self.bits_used == 8 {
+                self.flush_byte();
+            }
+        }
+    }
+
+    /// Pad the pending partial byte with zeros and write it out.
+    pub fn finish(&mut self) {
+        if self.bits_used > 0 {
+            self.current <<= 8 - self.bits_used;
+            self.flush_byte();
+        }
+    }
+
+    pub fn into_inner(self) -> CloneByteBuffer {
+        self.buffer
+    }
+
+    fn flush_byte(&mut self) {
+        if !self.buffer.has_remaining() {
+            // use the room between limit and capacity before growing
+            let cap = self.buffer.cap();
+            if self.buffer.limit() < cap {
+                self.buffer.limit_(cap);
+            } else {
+                self.buffer.grow(cap.max(8));
+            }
+        }
+        self.buffer.put(self.current);
+        self.current = 0;
+        self.bits_used = 0;
+    }
+}
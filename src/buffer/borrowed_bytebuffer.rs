@@ -0,0 +1,153 @@
+use crate::buffer::buffer::{IBuffer, ByteBuffer};
+
+/// Read-only buffer over a borrowed slice, e.g. constant lookup tables or test
+/// vectors, without the `to_vec()` copy the other constructors make.
+#[derive(Debug, Clone)]
+pub struct BorrowedByteBuffer<'a> {
+    pub buffer: ByteBuffer,
+    hb: &'a [u8],
+    pub offset: i32,
+}
+
+impl<'a> IBuffer for BorrowedByteBuffer<'a> {
+    fn mark(&self) -> i32 {
+        self.buffer.mark()
+    }
+
+    fn cap(&self) -> i32 {
+        self.buffer.cap()
+    }
+
+    fn position(&self) -> i32 {
+        self.buffer.position()
+    }
+
+    fn limit(&self) -> i32 {
+        self.buffer.limit()
+    }
+
+    fn reset(&mut self) -> &mut Self {
+        self.buffer.reset();
+        self
+    }
+
+    fn limit_(&mut self, limit: i32) -> &mut Self {
+        self.buffer.limit_(limit);
+        self
+    }
+
+    fn position_(&mut self, position: i32) -> &mut Self {
+        self.buffer.position_(position);
+        self
+    }
+
+    fn mark_(&mut self) -> &mut Self {
+        self.buffer.mark_();
+        self
+    }
+
+    fn clear(&mut self) -> &mut Self {
+        self.buffer.clear();
+        self
+    }
+
+    fn truncate(&mut self) -> &mut Self {
+        self.buffer.truncate();
+        self
+    }
+
+    fn flip(&mut self) -> &mut Self {
+        self.buffer.flip();
+        self
+    }
+
+    fn rewind(&mut self) -> &mut Self {
+        self.buffer.rewind();
+        self
+    }
+
+    fn slice(&self) -> &Self {
+        self.buffer.slice();
+        self
+    }
+
+    fn get(&mut self) -> u8 {
+        BorrowedByteBuffer::get(self)
+    }
+
+    fn put(&mut self, _x: u8) {
+        panic!("read only buffer!")
+    }
+}
+
+impl<'a> BorrowedByteBuffer<'a> {
+    /// Wrap `buf` without copying: position 0, limit = capacity = `buf.len()`.
+    pub fn new(buf: &'a [u8]) -> Self {
+        let len = buf.len() as i32;
+        let mut buffer = ByteBuffer::new_(-1, 0, len, len);
+        buffer.read_only = true;
+        Self {
+            buffer,
+            hb: buf,
+            offset: 0,
+        }
+    }
+
+    pub fn ix(&self, i: i32) -> i32 {
+        i + self.offset
+    }
+
+    /// The remaining bytes, borrowed for `'a` rather than from `self`.
+    pub fn as_slice(&self) -> &'a [u8] {
+        &self.hb[self.ix(self.position()) as usize..self.ix(self.limit()) as usize]
+    }
+
+    pub fn get(&mut self) -> u8 {
+        let idx = self.buffer.buffer.next_get_index();
+        self.hb[self.ix(idx) as usize]
+    }
+
+    pub fn get_i(&mut self, i: i32) -> u8 {
+        let idx = self.buffer.buffer.check_index(i);
+        self.hb[self.ix(idx) as usize]
+    }
+
+    pub fn get_i16(&mut self) -> i16 {
+        i16::from_be_bytes(self.get_array())
+    }
+
+    pub fn get_i32(&mut self) -> i32 {
+        i32::from_be_bytes(self.get_array())
+    }
+
+    pub fn get_i64(&mut self) -> i64 {
+        i64::from_be_bytes(self.get_array())
+    }
+
+    pub fn get_f32(&mut self) -> f32 {
+        f32::from_be_bytes(self.get_array())
+    }
+
+    pub fn get_f64(&mut self) -> f64 {
+        f64::from_be_bytes(self.get_array())
+    }
+
+    pub fn get_u128(&mut self) -> u128 {
+        u128::from_be_bytes(self.get_array())
+    }
+
+    fn get_array<const N: usize>(&mut self) -> [u8; N] {
+        let idx = self.buffer.buffer.next_get_index_nb(N as i32);
+        let start = self.ix(idx) as usize;
+        let mut bytes = [0u8; N];
+        bytes.copy_from_slice(&self.hb[start..start + N]);
+        bytes
+    }
+}
+
+impl BorrowedByteBuffer<'static> {
+    /// Zero-copy read-only buffer over compile-time data.
+    pub fn from_static(data: &'static [u8]) -> Self {
+        Self::new(data)
+    }
+}
\ No newline at end of file
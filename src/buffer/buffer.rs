@@ -1,3 +1,6 @@
+use std::io::{ErrorKind, Read};
+use crate::buffer::error::BufferError;
+
 #[derive(Debug, Clone)]
 pub struct Buffer {
     pub mark: i32,
@@ -22,7 +25,16 @@ pub trait IBuffer {
 
     fn clear(&mut self) -> &mut Self;
 
-    fn truncate(&mut self);
+    fn truncate(&mut self) -> &mut Self;
+
+    /// Consuming `truncate`, for the end of a chain that owns the buffer.
+    fn into_truncated(mut self) -> Self
+    where
+        Self: Sized,
+    {
+        self.truncate();
+        self
+    }
 
     fn flip(&mut self) -> &mut Self;
 
@@ -39,6 +51,52 @@ pub trait IBuffer {
     fn slice(&self) -> &Self;
 
     fn get(&mut self) -> u8;
+
+    /// Write `x` at the position and advance it. Buffers with backing bytes
+    /// override this; the default, kept by the bare `Buffer` cursor, panics.
+    fn put(&mut self, _x: u8) {
+        panic!("put is not supported by this buffer!")
+    }
+
+    /// Fallible `get`: `Err(Underflow)` instead of a panic when nothing remains.
+    fn try_get(&mut self) -> Result<u8, BufferError> {
+        if !self.has_remaining() {
+            return Err(BufferError::Underflow);
+        }
+        Ok(self.get())
+    }
+
+    /// Fallible `put`: `Err(Overflow)` instead of a panic when the buffer is full.
+    fn try_put(&mut self, x: u8) -> Result<(), BufferError> {
+        if !self.has_remaining() {
+            return Err(BufferError::Overflow);
+        }
+        self.put(x);
+        Ok(())
+    }
+
+    /// Read bytes from `r` into the buffer until it is full or `r` hits EOF,
+    /// returning the number of bytes read. The default goes through `put` one
+    /// byte at a time; concrete buffers override it with a bulk copy.
+    fn fill_from<R: Read>(&mut self, r: &mut R) -> std::io::Result<i32>
+    where
+        Self: Sized,
+    {
+        let mut n = 0;
+        let mut byte = [0u8; 1];
+        while self.has_remaining() {
+            match r.read(&mut byte) {
+                Ok(0) => break,
+                Ok(_) => {
+                    self.put(byte[0]);
+                    n += 1;
+                }
+                Err(ref e) if e.kind() == ErrorKind::Interrupted => {}
+                Err(e) => return Err(e),
+            }
+        }
+        Ok(n)
+    }
 }
 
 impl IBuffer for Buffer {
@@ -101,11 +159,12 @@ impl IBuffer for Buffer {
         self
     }
 
-    fn truncate(&mut self) {
+    fn truncate(&mut self) -> &mut Self {
         self.mark = -1;
         self.position = 0;
         self.limit = 0;
         self.cap = 0;
+        self
     }
 
     fn flip(&mut self) -> &mut Self {
@@ -179,6 +238,44 @@ impl Buffer {
         }
     }
 
+    /// Like `limit_`, but clamps `limit` into `[0, cap]` instead of panicking.
+    pub fn limit_clamped(&mut self, limit: i32) -> &mut Self {
+        let limit = limit.max(0).min(self.cap);
+        self.limit_(limit)
+    }
+
+    /// Like `clear`, but keeps the mark. Not NIO semantics: after a clear the
+    /// limit is the capacity, so the mark can only be dropped if it exceeds it.
+    pub fn clear_keep_mark(&mut self) -> &mut Self {
+        self.position = 0;
+        self.limit = self.cap;
+        if self.mark > self.limit {
+            self.mark = -1;
+        }
+        self
+    }
+
+    /// Like `flip`, but sets the limit to `limit` instead of the current position.
+    pub fn flip_to(&mut self, limit: i32) -> &mut Self {
+        if limit > self.cap || limit < 0 {
+            panic!("illegal argument!")
+        }
+        self.limit = limit;
+        self.position = 0;
+        self.mark = -1;
+        self
+    }
+
+    /// `remaining()` as a `usize`, for indexing and allocation sizes. Panics
+    /// instead of wrapping when the buffer is inverted (position past limit).
+    pub fn remaining_usize(&self) -> usize {
+        let n = self.remaining();
+        if n < 0 {
+            panic!("negative remaining: position {} > limit {}", self.position, self.limit)
+        }
+        n as usize
+    }
+
     pub fn discard_mark(&mut self) {
         self.mark = -1;
     }
@@ -221,29 +318,43 @@ impl Buffer {
 
     pub fn check_index(&mut self, i: i32) -> i32 {
         if i < 0 || i >= self.limit {
-            panic!("index out of bound")
+            panic!("index out of bounds: tried to access 1 byte at index {} but limit is {}", i, self.limit)
         }
         i
     }
 
+    /// Check that the `nb` bytes starting at `i` are below the limit.
     pub fn check_index_nb(&mut self, i: i32, nb: i32) -> i32 {
-        if i < 0 || nb >= self.limit - i {
-            panic!("index out of bound")
+        if i < 0 || nb > self.limit - i {
+            panic!(
+                "index out of bounds: tried to access {} bytes at index {} but limit is {}",
+                nb, i, self.limit
+            )
         }
         i
     }
 
+    /// Check that `[off, off + len)` lies within a sequence of `size` elements.
     pub fn check_bounds(off: i32, len: i32, size: i32) {
-        if (off | len | (off + len) | (size - (off + len))) < 0 {
-            panic!("index out of bounds!")
+        // in i64 so that `off + len` can't overflow
+        if off < 0 || len < 0 || off as i64 + len as i64 > size as i64 {
+            panic!(
+                "index out of bounds: tried to access {} elements at offset {} but size is {}",
+                len, off, size
+            )
         }
     }
 }
 
+/// The NIO `ByteBuffer` state. Built with `new_` it is index-only: the heap
+/// buffers embed it as their cursor and keep the bytes themselves. Built with
+/// `wrap` it owns its bytes in `hb`, so `get`/`put` work through `IBuffer`.
 #[derive(Debug, Clone)]
 pub struct ByteBuffer {
     pub buffer: Buffer,
     pub read_only: bool,
+    // empty for an index-only buffer
+    pub hb: Vec<u8>,
 }
 
 impl ByteBuffer {
@@ -266,6 +377,42 @@ impl ByteBuffer {
         Self {
             buffer,
             read_only: false,
+            hb: Vec::new(),
+        }
+    }
+
+    /// NIO `wrap(array)`: a buffer owning `hb`, with position 0 and
+    /// limit = capacity = `hb.len()`.
+    pub fn wrap(hb: Vec<u8>) -> Self {
+        let cap = hb.len() as i32;
+        let mut bb = Self::new_(-1, 0, cap, cap);
+        bb.hb = hb;
+        bb
+    }
+
+    fn byte_mut(&mut self, idx: i32) -> &mut u8 {
+        match self.hb.get_mut(idx as usize) {
+            Some(b) => b,
+            None => panic!("index-only buffer has no bytes, see ByteBuffer::wrap"),
+        }
+    }
+
+    /// NIO `asReadOnlyBuffer()`: same state as `self`, but every put-style method
+    /// panics.
+    pub fn as_read_only(&self) -> ByteBuffer {
+        let mut bb = self.clone();
+        bb.read_only = true;
+        bb
+    }
+
+    pub fn is_read_only(&self) -> bool {
+        self.read_only
+    }
+
+    /// Panic if the buffer is read-only. Put-style methods call this before writing.
+    pub fn check_writable(&self) {
+        if self.read_only {
+            panic!("read only buffer!")
         }
     }
 }
@@ -312,8 +459,9 @@ impl IBuffer for ByteBuffer {
         self
     }
 
-    fn truncate(&mut self) {
-        self.buffer.truncate()
+    fn truncate(&mut self) -> &mut Self {
+        self.buffer.truncate();
+        self
     }
 
     fn flip(&mut self) -> &mut Self {
@@ -332,6 +480,13 @@ impl IBuffer for ByteBuffer {
     }
 
     fn get(&mut self) -> u8 {
-        unimplemented!()
+        let idx = self.buffer.next_get_index();
+        *self.byte_mut(idx)
+    }
+
+    fn put(&mut self, x: u8) {
+        self.check_writable();
+        let idx = self.buffer.next_put_index();
+        *self.byte_mut(idx) = x;
     }
 }
\ No newline at end of file
@@ -0,0 +1,24 @@
+/// Byte order of multi-byte values, as in `java.nio.ByteOrder`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ByteOrder {
+    BigEndian,
+    LittleEndian,
+}
+
+impl ByteOrder {
+    /// The byte order of the host.
+    pub fn native() -> Self {
+        if cfg!(target_endian = "big") {
+            ByteOrder::BigEndian
+        } else {
+            ByteOrder::LittleEndian
+        }
+    }
+}
+
+impl Default for ByteOrder {
+    /// Big-endian, like a freshly allocated Java `ByteBuffer`.
+    fn default() -> Self {
+        ByteOrder::BigEndian
+    }
+}
\ No newline at end of file
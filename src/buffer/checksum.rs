@@ -0,0 +1,36 @@
+/// IEEE CRC32 (as used by zip, png and ethernet), computed with a 256-entry lookup table.
+const POLY: u32 = 0xEDB8_8320;
+
+const TABLE: [u32; 256] = make_table();
+
+const fn make_table() -> [u32; 256] {
+    let mut table = [0u32; 256];
+    let mut i = 0;
+    while i < 256 {
+        let mut crc = i as u32;
+        let mut k = 0;
+        while k < 8 {
+            crc = if crc & 1 == 1 { (crc >> 1) ^ POLY } else { crc >> 1 };
+            k += 1;
+        }
+        table[i] = crc;
+        i += 1;
+    }
+    table
+}
+
+/// CRC32 of `data`.
+pub fn crc32(data: &[u8]) -> u32 {
+    let mut crc = !0;
+    for &b in data {
+        crc = crc32_step(crc, b);
+    }
+    !crc
+}
+
+/// Feed one byte into a running (pre-inverted) crc state. Start from `!0`
+/// and invert the final state to get the checksum.
+#[inline]
+pub(crate) fn crc32_step(crc: u32, b: u8) -> u32 {
+    TABLE[((crc ^ b as u32) & 0xff) as usize] ^ (crc >> 8)
+}
@@ -1,12 +1,61 @@
-use std::cell::RefCell;
+use std::cell::{Ref, RefCell};
+use std::cmp::Ordering;
+use std::collections::VecDeque;
+use std::hash::{Hash, Hasher};
+use std::io::{self, BufRead, ErrorKind, IoSliceMut, Read, SeekFrom};
+use std::ops::{Deref, DerefMut};
+#[cfg(feature = "trace")]
+use std::fmt;
+use std::rc::Rc;
 use crate::buffer::buffer::{IBuffer, Buffer, ByteBuffer};
+use crate::buffer::byte_order::ByteOrder;
+use crate::buffer::checksum;
+use crate::bytebuffer::ByteBuffer as FfiByteBuffer;
+use crate::buffer::error::BufferError;
+#[cfg(feature = "pod")]
+use crate::buffer::pod::Pod;
+#[cfg(feature = "arrayvec")]
+use arrayvec::ArrayVec;
 
-#[derive(Debug, Clone)]
+/// Size of the header written by `CloneByteBuffer::to_ffi_with_header`:
+/// 4-byte big-endian length, 1-byte order flag, 1-byte read-only flag, 2 reserved bytes.
+pub const FFI_HEADER_LEN: usize = 8;
+
+/// `Clone` is a deep copy (see `copy`); `slice`, `duplicate` and `share` give
+/// handles on the same storage instead.
+#[derive(Debug)]
 pub struct CloneByteBuffer {
     pub buffer: ByteBuffer,
-    // use RefCell for multiple slice buffer to share the same underlying buf
-    pub hb: RefCell<Vec<u8>>,
+    // Rc<RefCell> so that slices and duplicates share the same underlying buf
+    pub hb: Rc<RefCell<Vec<u8>>>,
     pub offset: i32,
+    // alignment guaranteed for `ix(0)`, see `new_aligned`
+    align: usize,
+    // byte order of the multi-byte accessors, big-endian by default
+    order: ByteOrder,
+    // `get` wraps to 0 at the limit instead of panicking, see `circular_`
+    circular: bool,
+    // allocated with `allocate_direct`, see `is_direct`
+    direct: bool,
+    // cap on the bytes the fallible reads may consume, see `set_read_budget`
+    read_budget: Option<u64>,
+    read_used: u64,
+    // overwritten bytes of the recent writes, see `enable_undo`
+    undo: Option<Box<UndoLog>>,
+    #[cfg(feature = "trace")]
+    trace: Option<Trace>,
+}
+
+/// Callback fired with `(old_pos, new_pos)` whenever the position moves.
+#[cfg(feature = "trace")]
+#[derive(Clone)]
+struct Trace(Rc<dyn Fn(i32, i32)>);
+
+#[cfg(feature = "trace")]
+impl fmt::Debug for Trace {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("Trace")
+    }
 }
 
 impl IBuffer for CloneByteBuffer {
@@ -27,17 +76,23 @@ impl IBuffer for CloneByteBuffer {
     }
 
     fn reset(&mut self) -> &mut Self {
+        let old = self.position();
         self.buffer.reset();
+        self.trace_move(old);
         self
     }
 
     fn limit_(&mut self, limit: i32) -> &mut Self {
+        let old = self.position();
         self.buffer.limit_(limit);
+        self.trace_move(old);
         self
     }
 
     fn position_(&mut self, position: i32) -> &mut Self {
+        let old = self.position();
         self.buffer.position_(position);
+        self.trace_move(old);
         self
     }
 
@@ -47,21 +102,31 @@ impl IBuffer for CloneByteBuffer {
     }
 
     fn clear(&mut self) -> &mut Self {
+        let old = self.position();
         self.buffer.clear();
+        self.read_used = 0;
+        self.trace_move(old);
         self
     }
 
-    fn truncate(&mut self) {
+    fn truncate(&mut self) -> &mut Self {
+        let old = self.position();
         self.buffer.clear();
+        self.trace_move(old);
+        self
     }
 
     fn flip(&mut self) -> &mut Self {
+        let old = self.position();
         self.buffer.flip();
+        self.trace_move(old);
         self
     }
 
     fn rewind(&mut self) -> &mut Self {
+        let old = self.position();
         self.buffer.rewind();
+        self.trace_move(old);
         self
     }
 
@@ -71,7 +136,42 @@ impl IBuffer for CloneByteBuffer {
     }
 
     fn get(&mut self) -> u8 {
-        self.buffer.get()
+        CloneByteBuffer::get(self)
+    }
+
+    fn put(&mut self, x: u8) {
+        CloneByteBuffer::put(self, x)
+    }
+
+    fn try_get(&mut self) -> Result<u8, BufferError> {
+        let [x] = self.try_get_array()?;
+        Ok(x)
+    }
+
+    fn try_put(&mut self, x: u8) -> Result<(), BufferError> {
+        self.try_put_bytes(&[x])
+    }
+
+    fn fill_from<R: Read>(&mut self, r: &mut R) -> std::io::Result<i32> {
+        self.buffer.check_writable();
+        let start = self.position();
+        // record the whole remaining region once: `r` may fill any part of it
+        let (from, to) = (self.ix(start) as usize, self.ix(self.limit()) as usize);
+        self.record_undo(from, to - from);
+        while self.has_remaining() {
+            let from = self.ix(self.position()) as usize;
+            let to = self.ix(self.limit()) as usize;
+            let read = r.read(&mut self.hb.borrow_mut()[from..to]);
+            match read {
+                Ok(0) => break,
+                Ok(n) => {
+                    self.position_(self.position() + n as i32);
+                }
+                Err(ref e) if e.kind() == ErrorKind::Interrupted => {}
+                Err(e) => return Err(e),
+            }
+        }
+        Ok(self.position() - start)
     }
 }
 
@@ -80,8 +180,17 @@ impl CloneByteBuffer {
         let buffer = ByteBuffer::new_(mark, pos, limit, cap);
         Self {
             buffer,
-            hb: RefCell::new(buf.to_vec()),
+            hb: Rc::new(RefCell::new(buf.to_vec())),
             offset: 0,
+            align: 1,
+            order: ByteOrder::BigEndian,
+            circular: false,
+            direct: false,
+            read_budget: None,
+            read_used: 0,
+            undo: None,
+            #[cfg(feature = "trace")]
+            trace: None,
         }
     }
 
@@ -93,54 +202,288 @@ impl CloneByteBuffer {
         }
         Self {
             buffer,
-            hb: RefCell::new(buf.to_vec()),
+            hb: Rc::new(RefCell::new(buf.to_vec())),
             offset: 0,
+            align: 1,
+            order: ByteOrder::BigEndian,
+            circular: false,
+            direct: false,
+            read_budget: None,
+            read_used: 0,
+            undo: None,
+            #[cfg(feature = "trace")]
+            trace: None,
         }
     }
 
+    /// Copy `buf` into a new buffer with position `off`, limit `off + len` and
+    /// capacity `buf.len()`. Doesn't validate `off` and `len`; see `wrap`.
     pub fn new3(buf: &[u8], off: i32, len: i32) -> Self {
         let buffer = ByteBuffer::new_(-1, off, off + len, buf.len() as i32);
         Self {
             buffer: buffer,
-            hb: RefCell::new(buf.to_vec()),
+            hb: Rc::new(RefCell::new(buf.to_vec())),
             offset: 0,
+            align: 1,
+            order: ByteOrder::BigEndian,
+            circular: false,
+            direct: false,
+            read_budget: None,
+            read_used: 0,
+            undo: None,
+            #[cfg(feature = "trace")]
+            trace: None,
         }
     }
 
+    /// NIO `allocate(cap)`: a zero-filled buffer with position 0, limit = capacity
+    /// = `cap` and no mark. Same as `new2(cap, cap)`.
+    pub fn allocate(cap: i32) -> Self {
+        Self::new2(cap, cap)
+    }
+
+    /// NIO `allocateDirect(cap)`. For now the bytes live on the heap like
+    /// `allocate`; the buffer is only flagged (see `is_direct`), so code ported
+    /// from Java keeps the distinction until an off-heap backing exists.
+    pub fn allocate_direct(cap: i32) -> Self {
+        let hb = RefCell::new(vec![0; cap as usize]);
+        let mut buffer = Self::new_(ByteBuffer::new_(-1, 0, cap, cap), hb, 0);
+        buffer.direct = true;
+        buffer
+    }
+
+    /// NIO `isDirect()`: whether the buffer came from `allocate_direct`. Slices and
+    /// duplicates of a direct buffer are direct too.
+    pub fn is_direct(&self) -> bool {
+        self.direct
+    }
+
+    /// NIO `wrap(array, offset, length)`: a buffer over a copy of `buf` with
+    /// position `offset`, limit `offset + length`, capacity `buf.len()` and no mark.
+    /// Panics if the range doesn't fit in `buf`.
+    pub fn wrap(buf: &[u8], offset: i32, length: i32) -> Self {
+        Buffer::check_bounds(offset, length, buf.len() as i32);
+        Self::new3(buf, offset, length)
+    }
+
     pub fn new_(buffer: ByteBuffer, hb: RefCell<Vec<u8>>, offset: i32) -> Self {
         Self {
-            buffer, hb, offset
+            buffer,
+            hb: Rc::new(hb),
+            offset,
+            align: 1,
+            order: ByteOrder::BigEndian,
+            circular: false,
+            direct: false,
+            read_budget: None,
+            read_used: 0,
+            undo: None,
+            #[cfg(feature = "trace")]
+            trace: None,
         }
     }
 
-    // todo: the result of RefCell clone is not expected: we want to change the slice and also change the parent buffer.
-    // but use clone() here will only change the slice hb buffer, not changing the parent buffer.
+    /// NIO `slice()`: a view of the remaining bytes, with position 0,
+    /// limit = capacity = `remaining()` and no mark.
     pub fn slice(&self) -> Self {
-        let buffer = ByteBuffer::new_(-1, 0, self.buffer.remaining(), self.buffer.remaining());
+        let mut buffer = ByteBuffer::new_(-1, 0, self.buffer.remaining(), self.buffer.remaining());
+        buffer.read_only = self.buffer.read_only;
         Self {
             buffer,
-            hb: self.hb.clone(),
+            hb: Rc::clone(&self.hb),
             offset: self.buffer.position() + self.offset,
+            align: 1,
+            order: self.order,
+            circular: self.circular,
+            direct: self.direct,
+            read_budget: self.read_budget,
+            read_used: self.read_used,
+            undo: None,
+            #[cfg(feature = "trace")]
+            trace: self.trace.clone(),
         }
     }
 
-    pub fn duplicate(self) -> Self {
+    /// NIO `duplicate()`: same capacity, position, limit and mark as `self`.
+    pub fn duplicate(&self) -> Self {
         Self {
-            buffer: self.buffer,
-            hb: self.hb,
+            buffer: self.buffer.clone(),
+            hb: Rc::clone(&self.hb),
             offset: self.offset,
+            align: self.align,
+            order: self.order,
+            circular: self.circular,
+            direct: self.direct,
+            read_budget: self.read_budget,
+            read_used: self.read_used,
+            undo: None,
+            #[cfg(feature = "trace")]
+            trace: self.trace.clone(),
         }
     }
 
+    /// NIO `asReadOnlyBuffer()`: a duplicate sharing the storage, on which every
+    /// writer (`put*`, `compact`, `swap_bytes_*`, `as_mut_ptr`, ...) panics with
+    /// "read only buffer!". Slices and duplicates of it are read-only too.
+    pub fn as_read_only(&self) -> Self {
+        let mut ro = self.duplicate();
+        ro.buffer.read_only = true;
+        ro
+    }
+
+    pub fn is_read_only(&self) -> bool {
+        self.buffer.read_only
+    }
+
+    /// Allocate a zero-filled buffer of `cap` bytes whose start (`ix(0)`) is aligned
+    /// to `align` bytes, e.g. 16 or 32 for SIMD. The backing store is over-allocated
+    /// and the data starts at an aligned `offset`.
+    ///
+    /// ## Caveats
+    ///
+    /// The alignment only holds for this allocation. Growing the buffer or copying it
+    /// (`copy`, `clone`) moves the bytes to a fresh allocation, and `align()` drops
+    /// back to 1.
+    pub fn new_aligned(cap: i32, align: usize) -> Self {
+        if !align.is_power_of_two() {
+            panic!("illegal argument!")
+        }
+        let buffer = ByteBuffer::new_(-1, 0, cap, cap);
+        let hb = vec![0u8; cap as usize + align - 1];
+        let offset = hb.as_ptr().align_offset(align) as i32;
+        let mut aligned = Self::new_(buffer, RefCell::new(hb), offset);
+        aligned.align = align;
+        aligned
+    }
+
+    /// The alignment `ix(0)` is guaranteed to have: 1 unless built by `new_aligned`
+    /// and not reallocated since.
+    pub fn align(&self) -> usize {
+        self.align
+    }
+
+    /// Byte order used by the multi-byte accessors; `BigEndian` unless changed.
+    /// Slices and duplicates inherit it.
+    pub fn order(&self) -> ByteOrder {
+        self.order
+    }
+
+    pub fn order_(&mut self, order: ByteOrder) -> &mut Self {
+        self.order = order;
+        self
+    }
+
+    // big-endian `bytes` rearranged for `order`, and back
+    fn ordered<const N: usize>(order: ByteOrder, mut bytes: [u8; N]) -> [u8; N] {
+        if order == ByteOrder::LittleEndian {
+            bytes.reverse();
+        }
+        bytes
+    }
+
+    /// Install a callback invoked with `(old_pos, new_pos)` on every position change.
+    /// Useful to see exactly where a parser over-reads.
+    #[cfg(feature = "trace")]
+    pub fn set_trace(&mut self, f: impl Fn(i32, i32) + 'static) {
+        self.trace = Some(Trace(Rc::new(f)));
+    }
+
+    #[cfg(feature = "trace")]
+    fn trace_move(&self, old: i32) {
+        let new = self.position();
+        if let Some(trace) = &self.trace {
+            if old != new {
+                (trace.0)(old, new);
+            }
+        }
+    }
+
+    #[cfg(not(feature = "trace"))]
+    #[inline(always)]
+    fn trace_move(&self, _old: i32) {}
+
+    /// Set the limit, clamped into `[0, cap]`. See `Buffer::limit_clamped`.
+    pub fn limit_clamped(&mut self, limit: i32) -> &mut Self {
+        let old = self.position();
+        self.buffer.buffer.limit_clamped(limit);
+        self.trace_move(old);
+        self
+    }
+
+    /// Clear the buffer but keep the mark. See `Buffer::clear_keep_mark`.
+    pub fn clear_keep_mark(&mut self) -> &mut Self {
+        let old = self.position();
+        self.buffer.buffer.clear_keep_mark();
+        self.trace_move(old);
+        self
+    }
+
+    /// Flip the buffer with an explicit limit. See `Buffer::flip_to`.
+    pub fn flip_to(&mut self, limit: i32) -> &mut Self {
+        let old = self.position();
+        self.buffer.buffer.flip_to(limit);
+        self.trace_move(old);
+        self
+    }
+
     pub fn ix(&self, i: i32) -> i32 {
         i + self.offset
     }
 
     pub fn get(&mut self) -> u8 {
+        if self.circular {
+            return self.get_circular();
+        }
         let idx = self.buffer.buffer.next_get_index();
+        self.trace_move(idx);
         self.get_idx_(idx)
     }
 
+    /// `get` without the bounds check, for hot decode loops over trusted input.
+    /// Ignores `circular_`.
+    ///
+    /// ## Safety
+    ///
+    /// The caller must ensure `position() < limit()` and that the limit lies inside
+    /// the backing store, i.e. `ix(limit()) <= hb.borrow().len()`. Buffers from
+    /// `allocate`, `wrap`, `slice` and `duplicate` keep the latter by construction;
+    /// `new`, `new3` and `new_` don't check it, nor do writes to the pub fields.
+    #[inline]
+    pub unsafe fn get_unchecked(&mut self) -> u8 {
+        let pos = self.buffer.buffer.position;
+        let ix = self.ix(pos) as usize;
+        debug_assert!(pos < self.limit() && ix < self.hb.borrow().len());
+        self.buffer.buffer.position = pos + 1;
+        self.trace_move(pos);
+        *self.hb.borrow().get_unchecked(ix)
+    }
+
+    /// Like `get`, but at the limit wraps back to position 0 first, so a fixed
+    /// pattern can be replayed indefinitely. Still panics on an empty buffer.
+    pub fn get_circular(&mut self) -> u8 {
+        if self.position() == self.limit() && self.limit() > 0 {
+            let old = self.position();
+            self.buffer.buffer.position = 0;
+            self.trace_move(old);
+        }
+        let idx = self.buffer.buffer.next_get_index();
+        self.trace_move(idx);
+        self.get_idx_(idx)
+    }
+
+    /// Turn circular mode on or off: when on, `get` behaves like `get_circular`.
+    pub fn circular_(&mut self, circular: bool) -> &mut Self {
+        self.circular = circular;
+        self
+    }
+
+    pub fn is_circular(&self) -> bool {
+        self.circular
+    }
+
+    /// Byte `i` (relative to this buffer, below the limit); the position is not moved.
+    /// This is the indexed read: there is no `Index` impl, since a `&u8` into storage
+    /// shared with slices and duplicates could change or dangle under the caller.
     pub fn get_i(&mut self, i: i32) -> u8 {
         let idx = self.buffer.buffer.check_index(i);
         self.get_idx_(idx)
@@ -148,30 +491,29 @@ impl CloneByteBuffer {
 
     fn get_idx_(&mut self, i: i32) -> u8 {
         let ix = self.ix(i) as usize;
-        let mut hb = self.hb.get_mut();
-        hb[ix]
+        self.hb.borrow()[ix]
     }
 
     pub fn put(&mut self, x: u8) {
+        self.buffer.check_writable();
         let next_get_index = self.buffer.buffer.next_put_index();
+        self.trace_move(next_get_index);
         self.put_i(x, next_get_index)
     }
 
+    /// Write byte `i` (relative to this buffer, below the limit); the position is not moved.
     pub fn put_i(&mut self, x: u8, i: i32) {
+        self.buffer.check_writable();
         let idx = self.buffer.buffer.check_index(i);
         self.put_idx_(x, idx)
     }
 
     fn put_idx_(&mut self, x: u8, idx: i32) {
         let ix = self.ix(idx) as usize;
-        let mut hb = self.hb.get_mut();
-        hb[ix] = x;
+        self.record_undo(ix, 1);
+        self.hb.borrow_mut()[ix] = x;
     }
 
-    // todo: batch copy?
-    // System.arraycopy(hb, ix(position()), dst, offset, length);
-    // buf.append(hb[src_start..src_start+length]);
-    // buf[offset..offset+length] = hb[src_start..src_start+length];
     ///
     /// Get buf from HeapByteBuffer(source), copy to destination vec
     /// - source start: current HeapByteBuffer's position
@@ -179,39 +521,69 @@ impl CloneByteBuffer {
     ///
     pub fn get_buf(&mut self, dst: &mut Vec<u8>, offset: i32, length: i32) -> &mut Self {
         Buffer::check_bounds(offset, length, dst.len() as i32);
-        if length > self.remaining() {
+        if length as usize > self.buffer.buffer.remaining_usize() {
             panic!("buffer under flow")
         }
         let src_start = self.ix(self.position()) as usize;
-        let mut hb = self.hb.get_mut();
-        let mut idx = 0;
-        for i in offset..offset + length {
-            let id = i as usize;
-            dst[id] = hb[src_start+idx];
-            idx += 1;
-        }
-        assert_eq!(idx, length as usize);
+        let dst = &mut dst[offset as usize..(offset + length) as usize];
+        dst.copy_from_slice(&self.hb.borrow()[src_start..src_start + dst.len()]);
         self.position_(self.position() + length);
         self
     }
 
+    /// Like `get_buf`, but reads `length` `i32`s in `order()` into `dst[offset..]`,
+    /// advancing the position by `4 * length`.
+    // `&mut Vec` to match `get_buf`.
+    #[allow(clippy::ptr_arg)]
+    pub fn get_i32_buf(&mut self, dst: &mut Vec<i32>, offset: i32, length: i32) -> &mut Self {
+        Buffer::check_bounds(offset, length, dst.len() as i32);
+        if length as usize > self.buffer.buffer.remaining_usize() / 4 {
+            panic!("buffer under flow")
+        }
+        let src_start = self.ix(self.position()) as usize;
+        let hb = self.hb.borrow();
+        let src = &hb[src_start..src_start + 4 * length as usize];
+        let dst = &mut dst[offset as usize..(offset + length) as usize];
+        for (d, s) in dst.iter_mut().zip(src.chunks_exact(4)) {
+            *d = i32::from_be_bytes(Self::ordered(self.order, [s[0], s[1], s[2], s[3]]));
+        }
+        drop(hb);
+        self.position_(self.position() + 4 * length);
+        self
+    }
+
+    /// Fill `dst` from the current position, e.g. a stack `[u8; N]`.
+    /// Panics if fewer than `dst.len()` bytes remain.
+    pub fn read_into_slice(&mut self, dst: &mut [u8]) -> &mut Self {
+        let n = dst.len() as i32;
+        if n > self.remaining() {
+            panic!("buffer under flow")
+        }
+        let idx = self.buffer.buffer.next_get_index_nb(n);
+        let start = self.ix(idx) as usize;
+        dst.copy_from_slice(&self.hb.borrow()[start..start + n as usize]);
+        self.trace_move(idx);
+        self
+    }
+
     /// Put buf from source vector, to HeapByteBuffer
     /// - source start: offset
     /// - destination start: current HeapByteBuffer's position
     pub fn put_buf(&mut self, src: &mut Vec<u8>, offset: i32, length: i32) -> &mut Self {
+        self.put_slice(src, offset, length)
+    }
+
+    /// `put_buf` from any byte slice, e.g. `&static_data[..]` or `s.as_bytes()`.
+    pub fn put_slice(&mut self, src: &[u8], offset: i32, length: i32) -> &mut Self {
+        self.buffer.check_writable();
         Buffer::check_bounds(offset, length, src.len() as i32);
-        if length > self.remaining() {
-            panic!("buffer under flow")
+        if length as usize > self.buffer.buffer.remaining_usize() {
+            panic!("buffer over flow!")
         }
         let dst_start = self.ix(self.position()) as usize;
-        let mut hb = self.hb.get_mut();
-        let mut idx = 0;
-        for i in offset..offset + length {
-            let id = i as usize;
-            hb[dst_start +idx] = src[id];
-            idx += 1;
-        }
-        // assert_eq!(idx+1, length as usize);
+        let src = &src[offset as usize..(offset + length) as usize];
+        self.record_undo(dst_start, src.len());
+        self.hb.borrow_mut()[dst_start..dst_start + src.len()].copy_from_slice(src);
         self.position_(self.position() + length);
         self
     }
@@ -222,9 +594,10 @@ impl CloneByteBuffer {
     /// - source start: destination HeapByteBuffer's position
     /// - destination start: current HeapByteBuffer's position
     pub fn put_buffer(&mut self, heap_buffer: &mut CloneByteBuffer) {
+        self.buffer.check_writable();
         // let mut heap_buffer = buffer as HeapByteBuffer;
-        let n = heap_buffer.remaining() as usize;
-        if n > self.remaining() as usize {
+        let n = heap_buffer.buffer.buffer.remaining_usize();
+        if n > self.buffer.buffer.remaining_usize() {
             panic!("buffer overflow")
         }
 
@@ -232,20 +605,1506 @@ impl CloneByteBuffer {
         let src_start = heap_buffer.ix(heap_buffer.position()) as usize;
         let dst_start = self.ix(self.position()) as usize;
 
-        // mutable buf vector
-        let mut src_hb = heap_buffer.hb.get_mut();
-        let mut hb = self.hb.get_mut();
-
-        // copy from src_hb's src_start to hb's dst_start
-        let mut idx = 0;
-        for i in src_start..src_start + n {
-            let id = i as usize;
-            hb[dst_start+idx] = src_hb[id];
-            idx += 1;
+        self.record_undo(dst_start, n);
+        if Rc::ptr_eq(&self.hb, &heap_buffer.hb) {
+            // a slice or duplicate of `self`: the ranges may overlap, so memmove
+            self.hb.borrow_mut().copy_within(src_start..src_start + n, dst_start);
+        } else {
+            let src = heap_buffer.hb.borrow();
+            self.hb.borrow_mut()[dst_start..dst_start + n].copy_from_slice(&src[src_start..src_start + n]);
         }
+
         // update src and dst position
         heap_buffer.position_(heap_buffer.position() + n as i32);
         self.position_(self.position() + n as i32);
     }
 
-}
\ No newline at end of file
+    /// Structural equality: compare mark/position/limit/cap, the read-only flag,
+    /// the byte order, the offset and the whole backing store, not only the remaining bytes.
+    /// Handy for white-box assertions on the internal state of a buffer.
+    pub fn structural_eq(&self, other: &Self) -> bool {
+        self.mark() == other.mark()
+            && self.position() == other.position()
+            && self.limit() == other.limit()
+            && self.cap() == other.cap()
+            && self.buffer.read_only == other.buffer.read_only
+            && self.order == other.order
+            && self.offset == other.offset
+            && self.hb == other.hb
+    }
+
+    /// Byte-swap every 2-byte group of the remaining region in place. Position is not advanced.
+    pub fn swap_bytes_u16(&mut self) -> &mut Self {
+        self.swap_bytes(2)
+    }
+
+    /// Byte-swap every 4-byte group of the remaining region in place. Position is not advanced.
+    pub fn swap_bytes_u32(&mut self) -> &mut Self {
+        self.swap_bytes(4)
+    }
+
+    /// Byte-swap every 8-byte group of the remaining region in place. Position is not advanced.
+    pub fn swap_bytes_u64(&mut self) -> &mut Self {
+        self.swap_bytes(8)
+    }
+
+    fn swap_bytes(&mut self, width: i32) -> &mut Self {
+        self.buffer.check_writable();
+        if self.remaining() % width != 0 {
+            panic!("remaining is not a multiple of {}", width)
+        }
+        let start = self.ix(self.position()) as usize;
+        let end = self.ix(self.limit()) as usize;
+        self.record_undo(start, end - start);
+        let mut hb = self.hb.borrow_mut();
+        for group in hb[start..end].chunks_exact_mut(width as usize) {
+            group.reverse();
+        }
+        drop(hb);
+        self
+    }
+
+    /// Append `other`'s full data (`0..limit`, not only its remaining bytes) to the end
+    /// of this buffer's backing store, growing the capacity. Position is left untouched.
+    pub fn append_buffer(&mut self, other: &CloneByteBuffer) -> &mut Self {
+        self.buffer.check_writable();
+        let n = other.limit();
+        let src_start = other.ix(0) as usize;
+        // snapshot first: `other` may share storage with `self`
+        let src = other.hb.borrow()[src_start..src_start + n as usize].to_vec();
+        let dst_start = self.ix(self.cap()) as usize;
+        self.grow(n);
+        self.hb.borrow_mut()[dst_start..dst_start + n as usize].copy_from_slice(&src);
+        self
+    }
+
+    /// Grow the capacity by `additional` zero bytes inserted at the end of this buffer's
+    /// region of the backing store. A limit sitting at the old capacity follows it.
+    ///
+    /// Like a reallocation in NIO, growing a buffer whose storage is shared (with a
+    /// slice, duplicate or `share` handle) first gives it a private copy, so the
+    /// other handles never see their bytes shift.
+    pub(crate) fn grow(&mut self, additional: i32) {
+        self.buffer.check_writable();
+        if Rc::strong_count(&self.hb) > 1 {
+            let copy = self.hb.borrow().clone();
+            self.hb = Rc::new(RefCell::new(copy));
+        }
+        let end = self.ix(self.cap()) as usize;
+        let mut hb = self.hb.borrow_mut();
+        hb.splice(end..end, std::iter::repeat(0).take(additional as usize));
+        drop(hb);
+        // the splice may have reallocated
+        self.align = 1;
+        let buffer = &mut self.buffer.buffer;
+        if buffer.limit == buffer.cap {
+            buffer.limit += additional;
+        }
+        buffer.cap += additional;
+    }
+
+    /// Drop the bytes already read: move `[position, limit)` to the front of this
+    /// buffer's region, then set position to 0 and limit to the number of unread bytes.
+    /// Unlike a write-mode compact, the buffer stays ready for reading.
+    pub fn discard_consumed(&mut self) -> &mut Self {
+        self.buffer.check_writable();
+        let n = self.remaining();
+        let from = self.ix(self.position()) as usize;
+        let to = self.ix(self.limit()) as usize;
+        let dst = self.ix(0) as usize;
+        self.record_undo(dst, n as usize);
+        self.hb.borrow_mut().copy_within(from..to, dst);
+        let old = self.position();
+        let buffer = &mut self.buffer.buffer;
+        buffer.position = 0;
+        buffer.limit = n;
+        buffer.mark = -1;
+        self.trace_move(old);
+        self
+    }
+
+    /// NIO `compact()`: move `[position, limit)` to the front of this buffer's
+    /// region, then set position to the number of moved bytes and limit to the
+    /// capacity, ready for writing. Both ends go through `ix`, so a slice only
+    /// touches its own bytes.
+    pub fn compact(&mut self) -> &mut Self {
+        self.buffer.check_writable();
+        let n = self.remaining();
+        let from = self.ix(self.position()) as usize;
+        let to = self.ix(self.limit()) as usize;
+        let dst = self.ix(0) as usize;
+        self.record_undo(dst, n as usize);
+        self.hb.borrow_mut().copy_within(from..to, dst);
+        let old = self.position();
+        let buffer = &mut self.buffer.buffer;
+        buffer.position = n;
+        buffer.limit = buffer.cap;
+        buffer.mark = -1;
+        self.trace_move(old);
+        self
+    }
+
+    /// Copy `dst.len()` bytes from the current position into `dst` and return the
+    /// CRC32 of the copied bytes, computed in the same pass.
+    pub fn get_buf_checksummed(&mut self, dst: &mut [u8]) -> u32 {
+        let n = dst.len() as i32;
+        if n > self.remaining() {
+            panic!("buffer under flow")
+        }
+        let src_start = self.ix(self.position()) as usize;
+        let hb = self.hb.borrow();
+        let mut crc = !0;
+        for (d, &b) in dst.iter_mut().zip(&hb[src_start..src_start + n as usize]) {
+            *d = b;
+            crc = checksum::crc32_step(crc, b);
+        }
+        drop(hb);
+        self.position_(self.position() + n);
+        !crc
+    }
+
+    /// Copy the absolute range `[start, end)` out of the buffer, without touching position.
+    pub fn slice_to_vec(&self, start: i32, end: i32) -> Vec<u8> {
+        if start < 0 || start > end || end > self.cap() {
+            panic!("index out of bound")
+        }
+        let hb = self.hb.borrow();
+        hb[self.ix(start) as usize..self.ix(end) as usize].to_vec()
+    }
+
+    /// Write bytes from `iter` at the current position until the buffer is full or the
+    /// iterator ends, returning the number of bytes written. Items past the limit are
+    /// not pulled from the iterator.
+    pub fn put_iter(&mut self, iter: impl Iterator<Item = u8>) -> i32 {
+        self.buffer.check_writable();
+        let start = self.ix(self.position()) as usize;
+        // record everything the iterator may fill, it can stop early
+        self.record_undo(start, self.remaining() as usize);
+        let mut hb = self.hb.borrow_mut();
+        let mut n = 0;
+        for (d, b) in hb[start..].iter_mut().zip(iter.take(self.buffer.remaining() as usize)) {
+            *d = b;
+            n += 1;
+        }
+        drop(hb);
+        self.position_(self.position() + n);
+        n
+    }
+
+    /// Read a 16-byte Microsoft-style GUID (first three fields little-endian) and
+    /// return it in big-endian UUID byte order.
+    pub fn get_guid_le(&mut self) -> [u8; 16] {
+        let idx = self.buffer.buffer.next_get_index_nb(16);
+        let start = self.ix(idx) as usize;
+        let mut guid = [0u8; 16];
+        guid.copy_from_slice(&self.hb.borrow()[start..start + 16]);
+        guid[0..4].reverse();
+        guid[4..6].reverse();
+        guid[6..8].reverse();
+        self.trace_move(idx);
+        guid
+    }
+
+    /// Move the position like `std::io::Seek`; `End` is relative to the limit.
+    /// Returns the new position, which must land in `[0, limit]`.
+    pub fn seek(&mut self, from: SeekFrom) -> i32 {
+        let target = match from {
+            SeekFrom::Start(n) => (n <= i64::MAX as u64).then(|| n as i64),
+            SeekFrom::Current(delta) => (self.position() as i64).checked_add(delta),
+            SeekFrom::End(delta) => (self.limit() as i64).checked_add(delta),
+        };
+        let target = match target {
+            Some(t) if t >= 0 && t <= self.limit() as i64 => t,
+            _ => panic!("illegal argument!"),
+        };
+        self.position_(target as i32);
+        self.position()
+    }
+
+    /// Read a null-terminated UTF-8 string, consuming the terminator. If there is
+    /// no `0x00` before the limit, or the bytes are not UTF-8, the position is left
+    /// untouched.
+    pub fn get_cstr(&mut self) -> Result<String, BufferError> {
+        let start = self.ix(self.position()) as usize;
+        let end = self.ix(self.limit()) as usize;
+        let hb = self.hb.borrow();
+        let len = match hb[start..end].iter().position(|&b| b == 0) {
+            Some(len) => len,
+            None => return Err(BufferError::Underflow),
+        };
+        let s = std::str::from_utf8(&hb[start..start + len])?.to_string();
+        drop(hb);
+        self.position_(self.position() + len as i32 + 1);
+        Ok(s)
+    }
+
+    /// Write `s` followed by a `0x00` terminator. Panics if `s` contains a null byte.
+    pub fn put_cstr(&mut self, s: &str) {
+        self.buffer.check_writable();
+        if s.as_bytes().contains(&0) {
+            panic!("string contains an interior null byte")
+        }
+        let n = s.len() + 1;
+        let idx = self.buffer.buffer.next_put_index_nb(n as i32);
+        let start = self.ix(idx) as usize;
+        self.record_undo(start, n);
+        let mut hb = self.hb.borrow_mut();
+        hb[start..start + n - 1].copy_from_slice(s.as_bytes());
+        hb[start + n - 1] = 0;
+        self.trace_move(idx);
+    }
+
+    /// Fold over the remaining bytes without advancing the position.
+    pub fn fold_bytes<B>(&self, init: B, f: impl FnMut(B, u8) -> B) -> B {
+        let start = self.ix(self.position()) as usize;
+        let end = self.ix(self.limit()) as usize;
+        self.hb.borrow()[start..end].iter().copied().fold(init, f)
+    }
+
+    // Fallible typed accessors, in `order()`. On error the position is left untouched.
+
+    pub fn try_get_i16(&mut self) -> Result<i16, BufferError> {
+        Ok(i16::from_be_bytes(Self::ordered(self.order, self.try_get_array()?)))
+    }
+
+    pub fn try_get_i32(&mut self) -> Result<i32, BufferError> {
+        Ok(i32::from_be_bytes(Self::ordered(self.order, self.try_get_array()?)))
+    }
+
+    pub fn try_get_i64(&mut self) -> Result<i64, BufferError> {
+        Ok(i64::from_be_bytes(Self::ordered(self.order, self.try_get_array()?)))
+    }
+
+    pub fn try_get_f32(&mut self) -> Result<f32, BufferError> {
+        Ok(f32::from_be_bytes(Self::ordered(self.order, self.try_get_array()?)))
+    }
+
+    pub fn try_get_f64(&mut self) -> Result<f64, BufferError> {
+        Ok(f64::from_be_bytes(Self::ordered(self.order, self.try_get_array()?)))
+    }
+
+    pub fn try_put_i16(&mut self, x: i16) -> Result<(), BufferError> {
+        self.try_put_bytes(&Self::ordered(self.order, x.to_be_bytes()))
+    }
+
+    pub fn try_put_i32(&mut self, x: i32) -> Result<(), BufferError> {
+        self.try_put_bytes(&Self::ordered(self.order, x.to_be_bytes()))
+    }
+
+    pub fn try_put_i64(&mut self, x: i64) -> Result<(), BufferError> {
+        self.try_put_bytes(&Self::ordered(self.order, x.to_be_bytes()))
+    }
+
+    pub fn try_put_f32(&mut self, x: f32) -> Result<(), BufferError> {
+        self.try_put_bytes(&Self::ordered(self.order, x.to_be_bytes()))
+    }
+
+    pub fn try_put_f64(&mut self, x: f64) -> Result<(), BufferError> {
+        self.try_put_bytes(&Self::ordered(self.order, x.to_be_bytes()))
+    }
+
+    fn try_get_array<const N: usize>(&mut self) -> Result<[u8; N], BufferError> {
+        if self.remaining() < N as i32 {
+            return Err(BufferError::Underflow);
+        }
+        self.charge_read(N)?;
+        let idx = self.buffer.buffer.next_get_index_nb(N as i32);
+        let start = self.ix(idx) as usize;
+        let mut bytes = [0u8; N];
+        bytes.copy_from_slice(&self.hb.borrow()[start..start + N]);
+        self.trace_move(idx);
+        Ok(bytes)
+    }
+
+    fn try_put_bytes(&mut self, bytes: &[u8]) -> Result<(), BufferError> {
+        self.buffer.check_writable();
+        let n = bytes.len();
+        if self.remaining() < n as i32 {
+            return Err(BufferError::Overflow);
+        }
+        let idx = self.buffer.buffer.next_put_index_nb(n as i32);
+        let start = self.ix(idx) as usize;
+        self.record_undo(start, n);
+        self.hb.borrow_mut()[start..start + n].copy_from_slice(bytes);
+        self.trace_move(idx);
+        Ok(())
+    }
+
+    /// Raw pointer to the first remaining byte (`hb[ix(position())]`), for FFI or SIMD.
+    ///
+    /// ## Caveats
+    ///
+    /// Only `remaining()` bytes may be read through it. The pointer is invalidated by
+    /// anything that reallocates the backing store (e.g. `append_buffer` or other
+    /// growing writes) and by dropping the buffer. Do not read through it while a
+    /// write through the buffer (or a pointer from `as_mut_ptr`) is in progress.
+    pub fn as_ptr(&self) -> *const u8 {
+        let start = self.ix(self.position()) as usize;
+        self.hb.borrow()[start..].as_ptr()
+    }
+
+    /// Mutable raw pointer to the first remaining byte. Same caveats as `as_ptr`;
+    /// additionally no other reference into the backing store may be alive while
+    /// writing through it.
+    pub fn as_mut_ptr(&mut self) -> *mut u8 {
+        self.buffer.check_writable();
+        let start = self.ix(self.position()) as usize;
+        self.hb.borrow_mut()[start..].as_mut_ptr()
+    }
+
+    /// Decode one frame made of a 4-byte big-endian length followed by that many bytes.
+    /// Returns `Ok(None)` and leaves the position untouched if the frame is incomplete.
+    /// The whole frame is charged to the read budget only once it is complete;
+    /// `Err(BudgetExceeded)` also leaves the position untouched.
+    pub fn next_frame(&mut self) -> Result<Option<Vec<u8>>, BufferError> {
+        if self.remaining() < 4 {
+            return Ok(None);
+        }
+        let from = self.ix(self.position()) as usize;
+        let hb = self.hb.borrow();
+        let mut header = [0u8; 4];
+        header.copy_from_slice(&hb[from..from + 4]);
+        let len = u32::from_be_bytes(header) as i64;
+        if len > self.remaining() as i64 - 4 {
+            return Ok(None);
+        }
+        let body = hb[from + 4..from + 4 + len as usize].to_vec();
+        drop(hb);
+        self.charge_read(4 + body.len())?;
+        self.position_(self.position() + 4 + len as i32);
+        Ok(Some(body))
+    }
+
+    /// Encode one frame: a 4-byte big-endian length followed by `body`.
+    /// Counterpart of `next_frame`. If the frame doesn't fit and the limit sits at
+    /// the capacity, the buffer grows; a lower limit is a hard bound.
+    pub fn put_frame(&mut self, body: &[u8]) {
+        self.buffer.check_writable();
+        if body.len() > i32::MAX as usize - 4 {
+            panic!("buffer over flow!")
+        }
+        let n = body.len() as i32 + 4;
+        if n > self.remaining() && self.limit() == self.cap() {
+            self.grow(n - self.remaining());
+        }
+        let idx = self.buffer.buffer.next_put_index_nb(n);
+        let start = self.ix(idx) as usize;
+        self.record_undo(start, n as usize);
+        let mut hb = self.hb.borrow_mut();
+        hb[start..start + 4].copy_from_slice(&(body.len() as u32).to_be_bytes());
+        hb[start + 4..start + n as usize].copy_from_slice(body);
+        self.trace_move(idx);
+    }
+
+    /// Take a checkpoint of position/limit/mark. The returned guard derefs to the
+    /// buffer and restores the checkpoint on drop unless `Rollback::commit` is called.
+    pub fn checkpoint(&mut self) -> Rollback<'_> {
+        let state = (self.mark(), self.position(), self.limit());
+        Rollback {
+            buf: self,
+            state,
+            committed: false,
+        }
+    }
+
+    /// Reinterpret the remaining bytes as a slice of `T` without copying. Values are
+    /// in native byte order. Fails if the remaining region is not aligned for `T` or
+    /// its length is not a multiple of `size_of::<T>()`.
+    #[cfg(feature = "pod")]
+    pub fn view_as<T: Pod>(&self) -> Result<Ref<'_, [T]>, BufferError> {
+        let size = std::mem::size_of::<T>();
+        let n = self.remaining() as usize;
+        if size == 0 || n % size != 0 {
+            return Err(BufferError::SizeMismatch);
+        }
+        let start = self.ix(self.position()) as usize;
+        let hb = self.hb.borrow();
+        if hb[start..].as_ptr() as usize % std::mem::align_of::<T>() != 0 {
+            return Err(BufferError::Misaligned);
+        }
+        // Safety: the range is in bounds, aligned and of a whole number of `T`s,
+        // and `T: Pod` accepts any bit pattern.
+        Ok(Ref::map(hb, |hb| unsafe {
+            std::slice::from_raw_parts(hb[start..].as_ptr() as *const T, n / size)
+        }))
+    }
+
+    /// Pack the remaining bytes into an FFI `ByteBuffer`, prefixed with a header of
+    /// `FFI_HEADER_LEN` bytes so the other side can rebuild the buffer metadata:
+    ///
+    /// - 4 bytes: big-endian length of the data
+    /// - 1 byte: byte order, `0` for big-endian, `1` for little-endian
+    /// - 1 byte: `1` if read-only, else `0`
+    /// - 2 bytes: reserved, zero
+    pub fn to_ffi_with_header(&self) -> FfiByteBuffer {
+        let start = self.ix(self.position()) as usize;
+        let end = self.ix(self.limit()) as usize;
+        let hb = self.hb.borrow();
+        let mut v = Vec::with_capacity(FFI_HEADER_LEN + end - start);
+        v.extend_from_slice(&((end - start) as u32).to_be_bytes());
+        v.push((self.order == ByteOrder::LittleEndian) as u8);
+        v.push(self.buffer.read_only as u8);
+        v.extend_from_slice(&[0, 0]);
+        v.extend_from_slice(&hb[start..end]);
+        FfiByteBuffer::from_vec(v)
+    }
+
+    /// Rebuild a buffer from the output of `to_ffi_with_header`, taking ownership of
+    /// (and freeing) the FFI buffer. The result has position 0 and limit = cap = length.
+    pub fn from_ffi_with_header(bb: FfiByteBuffer) -> Result<Self, BufferError> {
+        let mut v = bb.destroy_into_vec();
+        if v.len() < FFI_HEADER_LEN {
+            return Err(BufferError::Underflow);
+        }
+        let mut len = [0u8; 4];
+        len.copy_from_slice(&v[0..4]);
+        let len = u32::from_be_bytes(len) as usize;
+        if v.len() - FFI_HEADER_LEN != len {
+            return Err(BufferError::SizeMismatch);
+        }
+        let order = match v[4] {
+            0 => ByteOrder::BigEndian,
+            1 => ByteOrder::LittleEndian,
+            _ => return Err(BufferError::InvalidState),
+        };
+        let read_only = v[5] != 0;
+        v.drain(..FFI_HEADER_LEN);
+        let mut buffer = ByteBuffer::new_(-1, 0, len as i32, len as i32);
+        buffer.read_only = read_only;
+        let mut bb = Self::new_(buffer, RefCell::new(v), 0);
+        bb.order = order;
+        Ok(bb)
+    }
+
+    /// Take up to `max` bytes from the current position (fewer if less remains),
+    /// advancing past them. Lets a caller drain a large buffer in bounded steps.
+    pub fn drain_chunk(&mut self, max: i32) -> Vec<u8> {
+        let n = max.max(0).min(self.remaining());
+        let start = self.ix(self.position()) as usize;
+        let chunk = self.hb.borrow()[start..start + n as usize].to_vec();
+        self.position_(self.position() + n);
+        chunk
+    }
+
+    /// Feed the remaining bytes into `state`, without a length prefix and without
+    /// copying them out first.
+    pub fn hash_into<H: Hasher>(&self, state: &mut H) {
+        let start = self.ix(self.position()) as usize;
+        let end = self.ix(self.limit()) as usize;
+        state.write(&self.hb.borrow()[start..end]);
+    }
+
+    /// Set position, limit and mark in one go, e.g. to restore a saved state.
+    /// The whole state is validated (`-1 <= mark <= position <= limit <= cap`) before
+    /// anything is applied, so, unlike `limit_` then `position_`, the mark is never
+    /// dropped on the way.
+    pub fn set_state(&mut self, position: i32, limit: i32, mark: i32) -> Result<&mut Self, BufferError> {
+        if mark < -1 || mark > position || position < 0 || position > limit || limit > self.cap() {
+            return Err(BufferError::InvalidState);
+        }
+        let old = self.position();
+        let buffer = &mut self.buffer.buffer;
+        buffer.position = position;
+        buffer.limit = limit;
+        buffer.mark = mark;
+        self.trace_move(old);
+        Ok(self)
+    }
+
+    /// Hand the whole remaining region to `sink` in one borrowed slice, then advance
+    /// the position to the limit.
+    pub fn drain_to(&mut self, mut sink: impl FnMut(&[u8])) {
+        let start = self.ix(self.position()) as usize;
+        let end = self.ix(self.limit()) as usize;
+        {
+            let hb = self.hb.borrow();
+            sink(&hb[start..end]);
+        }
+        let limit = self.limit();
+        self.position_(limit);
+    }
+
+    /// Build a new buffer alternating one byte from each input's remaining region,
+    /// e.g. two mono channels into one stereo stream. Both must have the same remaining.
+    pub fn interleave(&self, other: &CloneByteBuffer) -> CloneByteBuffer {
+        if self.remaining() != other.remaining() {
+            panic!("remaining lengths differ")
+        }
+        let a = self.hb.borrow();
+        let b = other.hb.borrow();
+        let a = &a[self.ix(self.position()) as usize..self.ix(self.limit()) as usize];
+        let b = &b[other.ix(other.position()) as usize..other.ix(other.limit()) as usize];
+        let mut v = Vec::with_capacity(a.len() * 2);
+        for (&x, &y) in a.iter().zip(b) {
+            v.push(x);
+            v.push(y);
+        }
+        Self::from_vec(v)
+    }
+
+    // position 0, limit = cap = v.len()
+    fn from_vec(v: Vec<u8>) -> Self {
+        let len = v.len() as i32;
+        Self::new_(ByteBuffer::new_(-1, 0, len, len), RefCell::new(v), 0)
+    }
+
+    /// Split the remaining region into `channels` buffers by stride: byte `i` goes to
+    /// channel `i % channels`. The remaining length must be a multiple of `channels`.
+    pub fn deinterleave(&self, channels: i32) -> Vec<CloneByteBuffer> {
+        if channels <= 0 || self.remaining() % channels != 0 {
+            panic!("remaining is not a multiple of {}", channels)
+        }
+        let hb = self.hb.borrow();
+        let data = &hb[self.ix(self.position()) as usize..self.ix(self.limit()) as usize];
+        (0..channels as usize)
+            .map(|c| Self::from_vec(data.iter().skip(c).step_by(channels as usize).copied().collect()))
+            .collect()
+    }
+
+    /// Left-pad the remaining region with `fill` up to `total` bytes: the data is
+    /// shifted right and the limit moved accordingly, growing the buffer if needed.
+    /// Does nothing if at least `total` bytes remain.
+    pub fn pad_start(&mut self, total: i32, fill: u8) {
+        self.buffer.check_writable();
+        let pad = total - self.remaining();
+        if pad <= 0 {
+            return;
+        }
+        let position = self.position();
+        let start = self.ix(position) as usize;
+        let end = self.ix(self.limit()) as usize;
+        let new_limit = position + total;
+        if new_limit > self.cap() {
+            self.grow(new_limit - self.cap());
+        }
+        self.record_undo(start, total as usize);
+        let mut hb = self.hb.borrow_mut();
+        hb.copy_within(start..end, start + pad as usize);
+        for b in &mut hb[start..start + pad as usize] {
+            *b = fill;
+        }
+        self.buffer.buffer.limit = new_limit;
+    }
+
+    /// Advance the position past leading `byte`s. Nothing is copied.
+    pub fn trim_start(&mut self, byte: u8) -> &mut Self {
+        let start = self.ix(self.position()) as usize;
+        let end = self.ix(self.limit()) as usize;
+        let n = self.hb.borrow()[start..end].iter().take_while(|&&b| b == byte).count();
+        self.position_(self.position() + n as i32);
+        self
+    }
+
+    /// Lower the limit past trailing `byte`s. Nothing is copied.
+    pub fn trim_end(&mut self, byte: u8) -> &mut Self {
+        let start = self.ix(self.position()) as usize;
+        let end = self.ix(self.limit()) as usize;
+        let n = self.hb.borrow()[start..end].iter().rev().take_while(|&&b| b == byte).count();
+        self.limit_(self.limit() - n as i32);
+        self
+    }
+
+    /// Read a `u128` in `order()`, e.g. an IPv6 address or a UUID as an integer.
+    pub fn get_u128(&mut self) -> u128 {
+        u128::from_be_bytes(Self::ordered(self.order, self.get_array()))
+    }
+
+    /// Write `x` as 16 bytes in `order()`.
+    pub fn put_u128(&mut self, x: u128) -> &mut Self {
+        self.put_bytes(&Self::ordered(self.order, x.to_be_bytes()))
+    }
+
+    fn get_array<const N: usize>(&mut self) -> [u8; N] {
+        let idx = self.buffer.buffer.next_get_index_nb(N as i32);
+        let start = self.ix(idx) as usize;
+        let mut bytes = [0u8; N];
+        bytes.copy_from_slice(&self.hb.borrow()[start..start + N]);
+        self.trace_move(idx);
+        bytes
+    }
+
+    fn put_bytes(&mut self, bytes: &[u8]) -> &mut Self {
+        self.buffer.check_writable();
+        let idx = self.buffer.buffer.next_put_index_nb(bytes.len() as i32);
+        let start = self.ix(idx) as usize;
+        self.record_undo(start, bytes.len());
+        self.hb.borrow_mut()[start..start + bytes.len()].copy_from_slice(bytes);
+        self.trace_move(idx);
+        self
+    }
+
+    /// A second handle on the same storage, keeping position, limit and mark.
+    /// Writes through either handle are visible through the other.
+    pub fn share(&self) -> Self {
+        self.duplicate()
+    }
+
+    /// An independent deep copy, keeping position, limit and mark. Writes through
+    /// either buffer are never visible through the other.
+    pub fn copy(&self) -> Self {
+        Self {
+            buffer: self.buffer.clone(),
+            hb: Rc::new(RefCell::new(self.hb.borrow().clone())),
+            offset: self.offset,
+            // the fresh allocation is not aligned
+            align: 1,
+            order: self.order,
+            circular: self.circular,
+            direct: self.direct,
+            read_budget: self.read_budget,
+            read_used: self.read_used,
+            undo: None,
+            #[cfg(feature = "trace")]
+            trace: self.trace.clone(),
+        }
+    }
+
+    /// Fill `bufs` in order from the current position, like `Read::read_vectored`.
+    /// Stops when the buffer runs dry and returns the number of bytes read.
+    pub fn read_vectored(&mut self, bufs: &mut [IoSliceMut<'_>]) -> i32 {
+        let old = self.position();
+        for dst in bufs.iter_mut() {
+            let n = dst.len().min(self.buffer.buffer.remaining_usize());
+            if n == 0 {
+                continue;
+            }
+            let start = self.ix(self.position()) as usize;
+            dst[..n].copy_from_slice(&self.hb.borrow()[start..start + n]);
+            self.buffer.buffer.position += n as i32;
+        }
+        self.trace_move(old);
+        self.position() - old
+    }
+
+    // `_with` variants read/write in an explicit order, for formats that mix
+    // endianness per field.
+
+    pub fn get_i16_with(&mut self, order: ByteOrder) -> i16 {
+        let bytes = self.get_array();
+        match order {
+            ByteOrder::BigEndian => i16::from_be_bytes(bytes),
+            ByteOrder::LittleEndian => i16::from_le_bytes(bytes),
+        }
+    }
+
+    pub fn get_i32_with(&mut self, order: ByteOrder) -> i32 {
+        let bytes = self.get_array();
+        match order {
+            ByteOrder::BigEndian => i32::from_be_bytes(bytes),
+            ByteOrder::LittleEndian => i32::from_le_bytes(bytes),
+        }
+    }
+
+    pub fn get_i64_with(&mut self, order: ByteOrder) -> i64 {
+        let bytes = self.get_array();
+        match order {
+            ByteOrder::BigEndian => i64::from_be_bytes(bytes),
+            ByteOrder::LittleEndian => i64::from_le_bytes(bytes),
+        }
+    }
+
+    pub fn get_f32_with(&mut self, order: ByteOrder) -> f32 {
+        let bytes = self.get_array();
+        match order {
+            ByteOrder::BigEndian => f32::from_be_bytes(bytes),
+            ByteOrder::LittleEndian => f32::from_le_bytes(bytes),
+        }
+    }
+
+    pub fn get_f64_with(&mut self, order: ByteOrder) -> f64 {
+        let bytes = self.get_array();
+        match order {
+            ByteOrder::BigEndian => f64::from_be_bytes(bytes),
+            ByteOrder::LittleEndian => f64::from_le_bytes(bytes),
+        }
+    }
+
+    pub fn put_i16_with(&mut self, x: i16, order: ByteOrder) -> &mut Self {
+        match order {
+            ByteOrder::BigEndian => self.put_bytes(&x.to_be_bytes()),
+            ByteOrder::LittleEndian => self.put_bytes(&x.to_le_bytes()),
+        }
+    }
+
+    pub fn put_i32_with(&mut self, x: i32, order: ByteOrder) -> &mut Self {
+        match order {
+            ByteOrder::BigEndian => self.put_bytes(&x.to_be_bytes()),
+            ByteOrder::LittleEndian => self.put_bytes(&x.to_le_bytes()),
+        }
+    }
+
+    pub fn put_i64_with(&mut self, x: i64, order: ByteOrder) -> &mut Self {
+        match order {
+            ByteOrder::BigEndian => self.put_bytes(&x.to_be_bytes()),
+            ByteOrder::LittleEndian => self.put_bytes(&x.to_le_bytes()),
+        }
+    }
+
+    pub fn put_f32_with(&mut self, x: f32, order: ByteOrder) -> &mut Self {
+        match order {
+            ByteOrder::BigEndian => self.put_bytes(&x.to_be_bytes()),
+            ByteOrder::LittleEndian => self.put_bytes(&x.to_le_bytes()),
+        }
+    }
+
+    pub fn put_f64_with(&mut self, x: f64, order: ByteOrder) -> &mut Self {
+        match order {
+            ByteOrder::BigEndian => self.put_bytes(&x.to_be_bytes()),
+            ByteOrder::LittleEndian => self.put_bytes(&x.to_le_bytes()),
+        }
+    }
+
+    /// Consume the buffer into the bytes `[0, limit)`. The backing `Vec` is handed
+    /// over without a copy when it starts at that region and is not shared.
+    pub fn into_vec(self) -> Vec<u8> {
+        let start = self.ix(0) as usize;
+        let end = self.ix(self.limit()) as usize;
+        match Rc::try_unwrap(self.hb) {
+            Ok(hb) if start == 0 => {
+                let mut hb = hb.into_inner();
+                hb.truncate(end);
+                hb
+            }
+            Ok(hb) => hb.into_inner()[start..end].to_vec(),
+            Err(hb) => hb.borrow()[start..end].to_vec(),
+        }
+    }
+
+    /// Snapshot of the buffer's state, e.g. for logging.
+    pub fn stats(&self) -> BufferStats {
+        BufferStats {
+            capacity: self.cap(),
+            used: self.limit(),
+            position: self.position(),
+            remaining: self.remaining(),
+            backing_len: self.hb.borrow().len(),
+            offset: self.offset,
+            read_only: self.buffer.read_only,
+        }
+    }
+
+    /// Read the next 2 bytes as an `i16` in `order()`.
+    pub fn get_i16(&mut self) -> i16 {
+        self.get_i16_with(self.order)
+    }
+
+    /// Read the next 4 bytes as an `i32` in `order()`.
+    pub fn get_i32(&mut self) -> i32 {
+        self.get_i32_with(self.order)
+    }
+
+    /// Read the next 8 bytes as an `i64` in `order()`.
+    pub fn get_i64(&mut self) -> i64 {
+        self.get_i64_with(self.order)
+    }
+
+    /// Write the low `nbytes` (1..=8) of `value` in `order()`, failing with
+    /// `ValueTooWide` if any higher bit is set.
+    pub fn put_uint_checked(&mut self, value: u64, nbytes: i32) -> Result<(), BufferError> {
+        let width = Self::uint_width(nbytes);
+        if width < 8 && value >> (width * 8) != 0 {
+            return Err(BufferError::ValueTooWide);
+        }
+        let field = self.uint_field(value, width);
+        self.try_put_bytes(&field[..width])
+    }
+
+    /// Write the low `nbytes` (1..=8) of `value` in `order()`, silently dropping
+    /// the higher bytes. Panics on overflow like `put`.
+    pub fn put_uint_truncating(&mut self, value: u64, nbytes: i32) -> &mut Self {
+        let width = Self::uint_width(nbytes);
+        let field = self.uint_field(value, width);
+        self.put_bytes(&field[..width])
+    }
+
+    fn uint_width(nbytes: i32) -> usize {
+        if !(1..=8).contains(&nbytes) {
+            panic!("illegal argument!")
+        }
+        nbytes as usize
+    }
+
+    // the low `width` bytes of `value` in `order()`, at the front of the array
+    fn uint_field(&self, value: u64, width: usize) -> [u8; 8] {
+        match self.order {
+            ByteOrder::BigEndian => {
+                let mut field = value.to_be_bytes();
+                field.rotate_left(8 - width);
+                field
+            }
+            ByteOrder::LittleEndian => value.to_le_bytes(),
+        }
+    }
+
+    /// Write `x` as 2 bytes in `order()`.
+    pub fn put_i16(&mut self, x: i16) -> &mut Self {
+        self.put_i16_with(x, self.order)
+    }
+
+    /// Write `x` as 4 bytes in `order()`.
+    pub fn put_i32(&mut self, x: i32) -> &mut Self {
+        self.put_i32_with(x, self.order)
+    }
+
+    /// Write `x` as 8 bytes in `order()`.
+    pub fn put_i64(&mut self, x: i64) -> &mut Self {
+        self.put_i64_with(x, self.order)
+    }
+
+    /// Read an unsigned LEB128 varint (protobuf encoding), at most 10 bytes.
+    pub fn get_varint(&mut self) -> u64 {
+        let mut x = 0u64;
+        for i in 0..10 {
+            let b = self.get();
+            x |= ((b & 0x7f) as u64) << (7 * i);
+            if b & 0x80 == 0 {
+                return x;
+            }
+        }
+        panic!("varint is longer than 10 bytes")
+    }
+
+    /// Write `x` as an unsigned LEB128 varint (protobuf encoding).
+    pub fn put_varint(&mut self, mut x: u64) -> &mut Self {
+        let mut bytes = [0u8; 10];
+        let mut n = 0;
+        loop {
+            bytes[n] = (x & 0x7f) as u8;
+            x >>= 7;
+            n += 1;
+            if x == 0 {
+                break;
+            }
+            bytes[n - 1] |= 0x80;
+        }
+        self.put_bytes(&bytes[..n])
+    }
+
+    /// Read a varint length followed by that many bytes, i.e. a protobuf `bytes` field.
+    pub fn get_bytes_prefixed(&mut self) -> Vec<u8> {
+        let len = self.get_varint();
+        if len > self.remaining() as u64 {
+            panic!("buffer under flow")
+        }
+        let mut v = vec![0u8; len as usize];
+        self.read_into_slice(&mut v);
+        v
+    }
+
+    /// Write `data.len()` as a varint followed by `data`.
+    pub fn put_bytes_prefixed(&mut self, data: &[u8]) -> &mut Self {
+        self.put_varint(data.len() as u64).put_bytes(data)
+    }
+
+    /// A `Read` + `BufRead` view over the remaining bytes, e.g. for `lines()`.
+    /// Reading through it advances this buffer's position.
+    pub fn reader(&mut self) -> Reader<'_> {
+        Reader {
+            hb: self.hb.borrow(),
+            buffer: &mut self.buffer.buffer,
+            offset: self.offset,
+            #[cfg(feature = "trace")]
+            trace: self.trace.clone(),
+        }
+    }
+
+    /// Byte-level diff of the remaining regions: `(index, self_byte, other_byte)`
+    /// for every index (relative to each position) where they differ. Past the end
+    /// of the shorter buffer its side is `None`, so every extra byte of the longer
+    /// one is reported, zeros included.
+    pub fn diff(&self, other: &CloneByteBuffer) -> Vec<(i32, Option<u8>, Option<u8>)> {
+        let a = self.hb.borrow();
+        let b = other.hb.borrow();
+        let a = &a[self.ix(self.position()) as usize..self.ix(self.limit()) as usize];
+        let b = &b[other.ix(other.position()) as usize..other.ix(other.limit()) as usize];
+        (0..a.len().max(b.len()))
+            .filter_map(|i| {
+                let x = a.get(i).copied();
+                let y = b.get(i).copied();
+                if x != y {
+                    Some((i as i32, x, y))
+                } else {
+                    None
+                }
+            })
+            .collect()
+    }
+
+    /// Apply `(index, byte)` edits at absolute indices, without moving the position.
+    /// All indices are checked against the limit before anything is written, so a
+    /// bad patch panics with the buffer untouched.
+    pub fn apply_patch(&mut self, patch: &[(i32, u8)]) -> &mut Self {
+        self.buffer.check_writable();
+        if patch.is_empty() {
+            return self;
+        }
+        for &(i, _) in patch {
+            self.buffer.buffer.check_index(i);
+        }
+        let offset = self.offset;
+        // one edit spanning every patched byte, so a single `undo` reverts the patch
+        let lo = patch.iter().map(|&(i, _)| i).min().unwrap();
+        let hi = patch.iter().map(|&(i, _)| i + 1).max().unwrap();
+        self.record_undo((lo + offset) as usize, (hi - lo) as usize);
+        let mut hb = self.hb.borrow_mut();
+        for &(i, b) in patch {
+            hb[(i + offset) as usize] = b;
+        }
+        drop(hb);
+        self
+    }
+
+    /// Read exactly `N` bytes into a stack array, e.g. a file signature, or return
+    /// `Err(Underflow)` without advancing if fewer remain.
+    pub fn read_exact<const N: usize>(&mut self) -> Result<[u8; N], BufferError> {
+        self.try_get_array()
+    }
+
+    /// Read `count` consecutive `T`s (e.g. a table of `#[repr(C)]` records) from the
+    /// current position, in native byte order. The whole table is bounds-checked
+    /// before anything is read. The source bytes need not be aligned: they are copied
+    /// into a `Vec<T>`, which is.
+    #[cfg(feature = "pod")]
+    pub fn read_structs<T: Pod>(&mut self, count: i32) -> Vec<T> {
+        let size = std::mem::size_of::<T>();
+        if count < 0 || size == 0 {
+            panic!("illegal argument!")
+        }
+        let nbytes = size.checked_mul(count as usize).filter(|&n| n <= i32::MAX as usize);
+        let nbytes = match nbytes {
+            Some(n) => n,
+            None => panic!("buffer under flow!"),
+        };
+        let p = self.buffer.buffer.next_get_index_nb(nbytes as i32);
+        let start = self.ix(p) as usize;
+        let hb = self.hb.borrow();
+        let src = &hb[start..start + nbytes];
+        let mut out: Vec<T> = Vec::with_capacity(count as usize);
+        // Safety: `out` has room for `count` aligned `T`s, `src` holds exactly that
+        // many bytes, and `T: Pod` accepts any bit pattern.
+        unsafe {
+            std::ptr::copy_nonoverlapping(src.as_ptr(), out.as_mut_ptr() as *mut u8, nbytes);
+            out.set_len(count as usize);
+        }
+        drop(hb);
+        self.trace_move(p);
+        out
+    }
+
+    /// Decode UTF-8 from at most `max_bytes` of the remaining bytes, for streams that
+    /// arrive in chunks. Returns the decoded text and the number of bytes consumed.
+    /// A multi-byte character cut off by `max_bytes` (or the limit) is left unread
+    /// for the next call; invalid sequences decode to U+FFFD.
+    pub fn get_utf8_chunk(&mut self, max_bytes: i32) -> (String, i32) {
+        if max_bytes < 0 {
+            panic!("illegal argument!")
+        }
+        let n = max_bytes.min(self.remaining()).max(0) as usize;
+        let start = self.ix(self.position()) as usize;
+        let hb = self.hb.borrow();
+        let mut bytes = &hb[start..start + n];
+        let mut s = String::new();
+        loop {
+            match std::str::from_utf8(bytes) {
+                Ok(valid) => {
+                    s.push_str(valid);
+                    bytes = &[];
+                    break;
+                }
+                Err(e) => {
+                    let (valid, rest) = bytes.split_at(e.valid_up_to());
+                    // Safety: `from_utf8` validated this prefix.
+                    s.push_str(unsafe { std::str::from_utf8_unchecked(valid) });
+                    match e.error_len() {
+                        Some(len) => {
+                            s.push(char::REPLACEMENT_CHARACTER);
+                            bytes = &rest[len..];
+                        }
+                        None => {
+                            bytes = rest;
+                            break;
+                        }
+                    }
+                }
+            }
+        }
+        let consumed = (n - bytes.len()) as i32;
+        drop(hb);
+        self.position_(self.position() + consumed);
+        (s, consumed)
+    }
+
+    /// Render the remaining bytes like `hexdump -C`: 16 bytes per row, each row
+    /// prefixed with its 8-digit hex offset from the position and followed by an
+    /// ASCII gutter (`.` for non-printable bytes), then a final row with the length.
+    pub fn to_hex(&self) -> String {
+        use std::fmt::Write;
+
+        let start = self.ix(self.position()) as usize;
+        let end = self.ix(self.limit()) as usize;
+        let hb = self.hb.borrow();
+        let bytes = &hb[start..end];
+        let mut out = String::new();
+        for (row, chunk) in bytes.chunks(16).enumerate() {
+            let _ = write!(out, "{:08x} ", row * 16);
+            for i in 0..16 {
+                if i == 8 {
+                    out.push(' ');
+                }
+                match chunk.get(i) {
+                    Some(b) => {
+                        let _ = write!(out, " {:02x}", b);
+                    }
+                    None => out.push_str("   "),
+                }
+            }
+            out.push_str("  |");
+            for &b in chunk {
+                out.push(if b.is_ascii_graphic() || b == b' ' { b as char } else { '.' });
+            }
+            out.push_str("|\n");
+        }
+        let _ = writeln!(out, "{:08x}", bytes.len());
+        out
+    }
+
+    /// IEEE CRC32 of the remaining bytes, without moving the position.
+    pub fn crc32(&self) -> u32 {
+        let start = self.ix(self.position()) as usize;
+        let end = self.ix(self.limit()) as usize;
+        checksum::crc32(&self.hb.borrow()[start..end])
+    }
+
+    /// Read up to `min(n, N, remaining())` bytes into a stack-backed `ArrayVec`,
+    /// for bounded reads without a heap allocation.
+    #[cfg(feature = "arrayvec")]
+    pub fn read_arrayvec<const N: usize>(&mut self, n: i32) -> ArrayVec<u8, N> {
+        let n = n.max(0).min(N as i32).min(self.remaining());
+        let mut out = ArrayVec::new();
+        let idx = self.buffer.buffer.next_get_index_nb(n);
+        let start = self.ix(idx) as usize;
+        out.try_extend_from_slice(&self.hb.borrow()[start..start + n as usize])
+            .expect("n <= N");
+        self.trace_move(idx);
+        out
+    }
+
+    /// Advance the position by `n` bytes without reading them.
+    pub fn skip(&mut self, n: i32) -> &mut Self {
+        if n < 0 {
+            panic!("illegal argument!")
+        }
+        let idx = self.buffer.buffer.next_get_index_nb(n);
+        self.trace_move(idx);
+        self
+    }
+
+    /// Index (relative to this buffer) of the first `byte` in the remaining region,
+    /// without moving the position.
+    pub fn find(&self, byte: u8) -> Option<i32> {
+        let start = self.ix(self.position()) as usize;
+        let end = self.ix(self.limit()) as usize;
+        self.hb.borrow()[start..end]
+            .iter()
+            .position(|&b| b == byte)
+            .map(|i| self.position() + i as i32)
+    }
+
+    /// Read up to the next `delim`, returning the bytes before it and leaving the
+    /// position just past it. `None`, without moving, if `delim` doesn't occur.
+    pub fn get_until(&mut self, delim: u8) -> Option<Vec<u8>> {
+        let at = self.find(delim)?;
+        let bytes = self.drain_chunk(at - self.position());
+        self.skip(1);
+        Some(bytes)
+    }
+
+    /// Cap the bytes the fallible reads (`try_get*`, `read_exact`, `try_get_buf`,
+    /// `next_frame`) may consume, e.g. so a forged length field can't force a huge read. Once
+    /// a read would go past the budget it returns `Err(BudgetExceeded)` without
+    /// advancing. The count starts over on `clear()`.
+    pub fn set_read_budget(&mut self, bytes: u64) -> &mut Self {
+        self.read_budget = Some(bytes);
+        self.read_used = 0;
+        self
+    }
+
+    /// Bytes left in the read budget, or `None` if there is no budget.
+    pub fn read_budget(&self) -> Option<u64> {
+        self.read_budget.map(|b| b.saturating_sub(self.read_used))
+    }
+
+    fn charge_read(&mut self, n: usize) -> Result<(), BufferError> {
+        if let Some(budget) = self.read_budget {
+            let used = self.read_used + n as u64;
+            if used > budget {
+                return Err(BufferError::BudgetExceeded);
+            }
+            self.read_used = used;
+        }
+        Ok(())
+    }
+
+    /// Fallible `read_into_slice`: `Err(Underflow)` if fewer than `dst.len()` bytes
+    /// remain, `Err(BudgetExceeded)` if the read budget doesn't cover them.
+    pub fn try_get_buf(&mut self, dst: &mut [u8]) -> Result<(), BufferError> {
+        if dst.len() > self.buffer.buffer.remaining_usize() {
+            return Err(BufferError::Underflow);
+        }
+        self.charge_read(dst.len())?;
+        self.read_into_slice(dst);
+        Ok(())
+    }
+
+    /// Copy out everything in `[position, limit)` and advance the position to the
+    /// limit. Unlike `into_vec`, the buffer stays usable.
+    pub fn get_all(&mut self) -> Vec<u8> {
+        let n = self.remaining();
+        self.drain_chunk(n)
+    }
+
+    /// Start recording writes (every `put*`, `fill_from`, `apply_patch`,
+    /// `swap_bytes_*`, `compact`, `discard_consumed` and `pad_start`) so they can be
+    /// reverted with `undo` and re-applied with `redo`, one call per write. Only the
+    /// overwritten bytes are kept, for the last `UNDO_LIMIT` writes; position and
+    /// limit are not restored, and writes through `as_mut_ptr` are not seen. Slices,
+    /// duplicates and copies start without a log.
+    pub fn enable_undo(&mut self) -> &mut Self {
+        if self.undo.is_none() {
+            self.undo = Some(Box::default());
+        }
+        self
+    }
+
+    /// Stop recording and drop the log.
+    pub fn disable_undo(&mut self) -> &mut Self {
+        self.undo = None;
+        self
+    }
+
+    /// Revert the most recent recorded write. Returns `false` if there is none.
+    pub fn undo(&mut self) -> bool {
+        let edit = match self.undo.as_mut().and_then(|log| log.done.pop_back()) {
+            Some(edit) => edit,
+            None => return false,
+        };
+        let edit = self.swap_edit(edit);
+        self.undo.as_mut().unwrap().undone.push(edit);
+        true
+    }
+
+    /// Re-apply the most recently undone write. Returns `false` if there is none;
+    /// any new write clears the redo history.
+    pub fn redo(&mut self) -> bool {
+        let edit = match self.undo.as_mut().and_then(|log| log.undone.pop()) {
+            Some(edit) => edit,
+            None => return false,
+        };
+        let edit = self.swap_edit(edit);
+        self.undo.as_mut().unwrap().done.push_back(edit);
+        true
+    }
+
+    // Put `edit`'s bytes back and return an edit holding the bytes they replaced.
+    fn swap_edit(&mut self, mut edit: Edit) -> Edit {
+        self.buffer.check_writable();
+        let mut hb = self.hb.borrow_mut();
+        let range = edit.at..edit.at + edit.bytes.len();
+        hb[range].swap_with_slice(&mut edit.bytes);
+        edit
+    }
+
+    fn record_undo(&mut self, at: usize, len: usize) {
+        if let Some(log) = self.undo.as_mut() {
+            if log.done.len() == UNDO_LIMIT {
+                log.done.pop_front();
+            }
+            let bytes = self.hb.borrow()[at..at + len].to_vec();
+            log.done.push_back(Edit { at, bytes });
+            log.undone.clear();
+        }
+    }
+
+    /// Look for a UTF-16 byte order mark at the position: `FE FF` selects big-endian,
+    /// `FF FE` little-endian. If one is found it is consumed and becomes the buffer's
+    /// `order`; otherwise nothing changes and `None` is returned.
+    pub fn detect_bom(&mut self) -> Option<ByteOrder> {
+        if self.remaining() < 2 {
+            return None;
+        }
+        let start = self.ix(self.position()) as usize;
+        let bom = {
+            let hb = self.hb.borrow();
+            [hb[start], hb[start + 1]]
+        };
+        let order = match bom {
+            [0xfe, 0xff] => ByteOrder::BigEndian,
+            [0xff, 0xfe] => ByteOrder::LittleEndian,
+            _ => return None,
+        };
+        self.skip(2);
+        self.order = order;
+        Some(order)
+    }
+
+    /// Read a one-byte tag and map it to an enum variant with `from`. On an unknown
+    /// tag returns `Err(InvalidDiscriminant(tag))` and leaves the position on it.
+    pub fn get_enum<T>(&mut self, from: impl Fn(u8) -> Option<T>) -> Result<T, BufferError> {
+        if !self.has_remaining() {
+            return Err(BufferError::Underflow);
+        }
+        let tag = self.get_idx_(self.position());
+        let value = from(tag).ok_or(BufferError::InvalidDiscriminant(tag))?;
+        self.skip(1);
+        Ok(value)
+    }
+
+    /// Write `s` as a 4-byte big-endian byte length followed by its UTF-8 bytes.
+    /// Panics, before writing anything, if they don't fit.
+    pub fn put_string(&mut self, s: &str) -> &mut Self {
+        if s.len() > i32::MAX as usize || 4 + s.len() > self.buffer.buffer.remaining_usize() {
+            panic!("buffer over flow!")
+        }
+        self.put_i32_with(s.len() as i32, ByteOrder::BigEndian).put_bytes(s.as_bytes())
+    }
+
+    /// Read a string written by `put_string`. On a short buffer (`Err(Underflow)`)
+    /// or invalid UTF-8 (`Err(InvalidUtf8)`) the position is left where it was.
+    pub fn get_string(&mut self) -> Result<String, BufferError> {
+        if self.remaining() < 4 {
+            return Err(BufferError::Underflow);
+        }
+        let start = self.ix(self.position()) as usize;
+        let hb = self.hb.borrow();
+        let len = i32::from_be_bytes([hb[start], hb[start + 1], hb[start + 2], hb[start + 3]]);
+        if len < 0 || len > self.remaining() - 4 {
+            return Err(BufferError::Underflow);
+        }
+        let bytes = &hb[start + 4..start + 4 + len as usize];
+        let s = std::str::from_utf8(bytes)?.to_string();
+        drop(hb);
+        self.skip(4 + len);
+        Ok(s)
+    }
+
+    /// Exchange everything (storage, position, limit, mark, offset, order, ...)
+    /// with `other` in O(1), e.g. to flip double buffers.
+    pub fn swap(&mut self, other: &mut CloneByteBuffer) {
+        std::mem::swap(self, other);
+    }
+
+    /// Hand the remaining bytes over to an FFI `ByteBuffer`, e.g. a message built
+    /// here and flipped. The backing `Vec` is moved rather than copied when the
+    /// remaining region starts it and it is not shared (see `into_vec`).
+    pub fn into_ffi(self) -> FfiByteBuffer {
+        let start = self.ix(self.position()) as usize;
+        if start == 0 {
+            return FfiByteBuffer::from_vec(self.into_vec());
+        }
+        let end = self.ix(self.limit()) as usize;
+        let bytes = self.hb.borrow()[start..end].to_vec();
+        FfiByteBuffer::from_vec(bytes)
+    }
+
+    /// Take over the bytes of an FFI `ByteBuffer` (see `destroy_into_vec`, whose
+    /// caveats about the allocator apply), with position 0 and limit = capacity =
+    /// their length. The allocation is moved, not copied, and `bb` is consumed, so
+    /// it can't be destroyed a second time.
+    pub fn from_ffi(bb: FfiByteBuffer) -> Self {
+        Self::from_vec(bb.destroy_into_vec())
+    }
+
+}
+
+/// Number of writes `CloneByteBuffer::undo` can go back.
+pub const UNDO_LIMIT: usize = 1024;
+
+#[derive(Debug, Default)]
+struct UndoLog {
+    done: VecDeque<Edit>,
+    undone: Vec<Edit>,
+}
+
+/// The bytes at backing index `at` before (or, on the redo side, after) a write.
+#[derive(Debug)]
+struct Edit {
+    at: usize,
+    bytes: Vec<u8>,
+}
+
+/// Returned by `CloneByteBuffer::stats`. `used` is the limit; `backing_len` is the
+/// length of the whole backing store, which a slice shares with its parent.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BufferStats {
+    pub capacity: i32,
+    pub used: i32,
+    pub position: i32,
+    pub remaining: i32,
+    pub backing_len: usize,
+    pub offset: i32,
+    pub read_only: bool,
+}
+
+impl Clone for CloneByteBuffer {
+    fn clone(&self) -> Self {
+        self.copy()
+    }
+}
+
+/// NIO `equals`: two buffers are equal if their remaining bytes are, whatever
+/// their positions, capacities or storage.
+impl PartialEq for CloneByteBuffer {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for CloneByteBuffer {}
+
+/// NIO `compareTo`: lexicographic, unsigned comparison of the remaining bytes.
+impl Ord for CloneByteBuffer {
+    fn cmp(&self, other: &Self) -> Ordering {
+        let a = self.hb.borrow();
+        let b = other.hb.borrow();
+        let a = &a[self.ix(self.position()) as usize..self.ix(self.limit()) as usize];
+        let b = &b[other.ix(other.position()) as usize..other.ix(other.limit()) as usize];
+        a.cmp(b)
+    }
+}
+
+impl PartialOrd for CloneByteBuffer {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+/// Hashes the remaining bytes, consistent with `PartialEq`. See `hash_into`.
+impl Hash for CloneByteBuffer {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.hash_into(state)
+    }
+}
+
+/// RAII guard returned by `CloneByteBuffer::checkpoint`.
+pub struct Rollback<'a> {
+    buf: &'a mut CloneByteBuffer,
+    // (mark, position, limit) at the checkpoint
+    state: (i32, i32, i32),
+    committed: bool,
+}
+
+impl<'a> Rollback<'a> {
+    /// Keep everything done since the checkpoint.
+    pub fn commit(mut self) {
+        self.committed = true;
+    }
+}
+
+impl<'a> Deref for Rollback<'a> {
+    type Target = CloneByteBuffer;
+
+    fn deref(&self) -> &CloneByteBuffer {
+        self.buf
+    }
+}
+
+impl<'a> DerefMut for Rollback<'a> {
+    fn deref_mut(&mut self) -> &mut CloneByteBuffer {
+        self.buf
+    }
+}
+
+impl<'a> Drop for Rollback<'a> {
+    fn drop(&mut self) {
+        if self.committed {
+            return;
+        }
+        let old = self.buf.position();
+        let (mark, position, limit) = self.state;
+        let buffer = &mut self.buf.buffer.buffer;
+        buffer.mark = mark;
+        buffer.position = position;
+        buffer.limit = limit;
+        self.buf.trace_move(old);
+    }
+}
+
+/// `Read`/`BufRead` adapter returned by `CloneByteBuffer::reader`, reading the
+/// remaining bytes and advancing the buffer's position as they are consumed.
+/// Holds a shared borrow of the backing store for its lifetime.
+pub struct Reader<'a> {
+    hb: Ref<'a, Vec<u8>>,
+    buffer: &'a mut Buffer,
+    offset: i32,
+    #[cfg(feature = "trace")]
+    trace: Option<Trace>,
+}
+
+impl<'a> Read for Reader<'a> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let src = self.fill_buf()?;
+        let n = src.len().min(buf.len());
+        buf[..n].copy_from_slice(&src[..n]);
+        self.consume(n);
+        Ok(n)
+    }
+}
+
+impl<'a> BufRead for Reader<'a> {
+    fn fill_buf(&mut self) -> io::Result<&[u8]> {
+        let start = (self.buffer.position + self.offset) as usize;
+        let end = (self.buffer.limit + self.offset) as usize;
+        Ok(&self.hb[start..end])
+    }
+
+    fn consume(&mut self, amt: usize) {
+        let amt = amt.min(self.buffer.remaining_usize()) as i32;
+        self.buffer.position += amt;
+        #[cfg(feature = "trace")]
+        if let Some(trace) = &self.trace {
+            if amt != 0 {
+                let new = self.buffer.position;
+                (trace.0)(new - amt, new);
+            }
+        }
+    }
+}
+/// Iterator over the remaining bytes of a `CloneByteBuffer`, returned by
+/// `(&buffer).into_iter()`. Doesn't move the buffer's position.
+pub struct CloneByteBufferIter<'a> {
+    hb: Ref<'a, Vec<u8>>,
+    next: usize,
+    end: usize,
+}
+
+impl<'a> Iterator for CloneByteBufferIter<'a> {
+    type Item = u8;
+
+    fn next(&mut self) -> Option<u8> {
+        if self.next == self.end {
+            return None;
+        }
+        self.next += 1;
+        Some(self.hb[self.next - 1])
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let n = self.end - self.next;
+        (n, Some(n))
+    }
+}
+
+impl<'a> ExactSizeIterator for CloneByteBufferIter<'a> {}
+
+impl<'a> IntoIterator for &'a CloneByteBuffer {
+    type Item = u8;
+    type IntoIter = CloneByteBufferIter<'a>;
+
+    fn into_iter(self) -> CloneByteBufferIter<'a> {
+        CloneByteBufferIter {
+            hb: self.hb.borrow(),
+            next: self.ix(self.position()) as usize,
+            end: self.ix(self.limit()) as usize,
+        }
+    }
+}
@@ -0,0 +1,57 @@
+use std::error::Error;
+use std::fmt;
+use std::str::Utf8Error;
+
+/// Errors returned by the fallible (`try_*` and friends) buffer operations.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum BufferError {
+    /// Not enough bytes remaining to complete the read.
+    Underflow,
+    /// Not enough room remaining to complete the write.
+    Overflow,
+    /// The bytes read are not valid UTF-8.
+    InvalidUtf8(Utf8Error),
+    /// The data is not aligned for the requested type.
+    Misaligned,
+    /// The data length is not a multiple of the requested type's size.
+    SizeMismatch,
+    /// The requested mark/position/limit break `-1 <= mark <= position <= limit <= cap`.
+    InvalidState,
+    /// The value does not fit in the requested number of bytes.
+    ValueTooWide,
+    /// The read would go past the budget set with `set_read_budget`.
+    BudgetExceeded,
+    /// The tag byte does not name any variant of the requested enum.
+    InvalidDiscriminant(u8),
+}
+
+impl fmt::Display for BufferError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BufferError::Underflow => f.write_str("buffer under flow"),
+            BufferError::Overflow => f.write_str("buffer over flow"),
+            BufferError::InvalidUtf8(e) => write!(f, "invalid utf-8: {}", e),
+            BufferError::Misaligned => f.write_str("misaligned data"),
+            BufferError::SizeMismatch => f.write_str("length is not a multiple of the type size"),
+            BufferError::InvalidState => f.write_str("invalid mark/position/limit"),
+            BufferError::ValueTooWide => f.write_str("value does not fit in the field"),
+            BufferError::BudgetExceeded => f.write_str("read budget exceeded"),
+            BufferError::InvalidDiscriminant(b) => write!(f, "invalid discriminant: {}", b),
+        }
+    }
+}
+
+impl Error for BufferError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            BufferError::InvalidUtf8(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<Utf8Error> for BufferError {
+    fn from(e: Utf8Error) -> Self {
+        BufferError::InvalidUtf8(e)
+    }
+}
@@ -0,0 +1,278 @@
+use std::cell::RefCell;
+use std::rc::Rc;
+use crate::buffer::buffer::{IBuffer, ByteBuffer};
+
+/// Backing store of a `GenericByteBuffer`. `share` decides what `slice` and
+/// `clone` do with it: copy it or hand out another handle on the same bytes.
+pub trait ByteStorage {
+    fn from_vec(v: Vec<u8>) -> Self;
+
+    fn with<R>(&self, f: impl FnOnce(&[u8]) -> R) -> R;
+
+    fn with_mut<R>(&mut self, f: impl FnOnce(&mut [u8]) -> R) -> R;
+
+    fn share(&self) -> Self;
+}
+
+/// Independent storage: `share` is a deep copy.
+impl ByteStorage for Vec<u8> {
+    fn from_vec(v: Vec<u8>) -> Self {
+        v
+    }
+
+    fn with<R>(&self, f: impl FnOnce(&[u8]) -> R) -> R {
+        f(self)
+    }
+
+    fn with_mut<R>(&mut self, f: impl FnOnce(&mut [u8]) -> R) -> R {
+        f(self)
+    }
+
+    fn share(&self) -> Self {
+        self.clone()
+    }
+}
+
+/// Shared storage: `share` is another handle on the same bytes.
+impl ByteStorage for Rc<RefCell<Vec<u8>>> {
+    fn from_vec(v: Vec<u8>) -> Self {
+        Rc::new(RefCell::new(v))
+    }
+
+    fn with<R>(&self, f: impl FnOnce(&[u8]) -> R) -> R {
+        f(&self.borrow())
+    }
+
+    fn with_mut<R>(&mut self, f: impl FnOnce(&mut [u8]) -> R) -> R {
+        f(&mut self.borrow_mut())
+    }
+
+    fn share(&self) -> Self {
+        Rc::clone(self)
+    }
+}
+
+/// A heap byte buffer over any `ByteStorage`; use `UniqueByteBuffer` or
+/// `SharedByteBuffer` rather than naming this directly.
+#[derive(Debug)]
+pub struct GenericByteBuffer<S: ByteStorage> {
+    pub buffer: ByteBuffer,
+    hb: S,
+    pub offset: i32,
+}
+
+/// Owns its bytes: `clone` and `slice` copy them, so writes never show up elsewhere.
+pub type UniqueByteBuffer = GenericByteBuffer<Vec<u8>>;
+
+/// Shares its bytes: `clone` and `slice` are views of the same storage, so writes
+/// through one show up in the others.
+pub type SharedByteBuffer = GenericByteBuffer<Rc<RefCell<Vec<u8>>>>;
+
+impl<S: ByteStorage> Clone for GenericByteBuffer<S> {
+    fn clone(&self) -> Self {
+        Self {
+            buffer: self.buffer.clone(),
+            hb: self.hb.share(),
+            offset: self.offset,
+        }
+    }
+}
+
+impl<S: ByteStorage> IBuffer for GenericByteBuffer<S> {
+    fn mark(&self) -> i32 {
+        self.buffer.mark()
+    }
+
+    fn cap(&self) -> i32 {
+        self.buffer.cap()
+    }
+
+    fn position(&self) -> i32 {
+        self.buffer.position()
+    }
+
+    fn limit(&self) -> i32 {
+        self.buffer.limit()
+    }
+
+    fn reset(&mut self) -> &mut Self {
+        self.buffer.reset();
+        self
+    }
+
+    fn limit_(&mut self, limit: i32) -> &mut Self {
+        self.buffer.limit_(limit);
+        self
+    }
+
+    fn position_(&mut self, position: i32) -> &mut Self {
+        self.buffer.position_(position);
+        self
+    }
+
+    fn mark_(&mut self) -> &mut Self {
+        self.buffer.mark_();
+        self
+    }
+
+    fn clear(&mut self) -> &mut Self {
+        self.buffer.clear();
+        self
+    }
+
+    fn truncate(&mut self) -> &mut Self {
+        self.buffer.truncate();
+        self
+    }
+
+    fn flip(&mut self) -> &mut Self {
+        self.buffer.flip();
+        self
+    }
+
+    fn rewind(&mut self) -> &mut Self {
+        self.buffer.rewind();
+        self
+    }
+
+    fn slice(&self) -> &Self {
+        self.buffer.slice();
+        self
+    }
+
+    fn get(&mut self) -> u8 {
+        GenericByteBuffer::get(self)
+    }
+
+    fn put(&mut self, x: u8) {
+        GenericByteBuffer::put(self, x)
+    }
+}
+
+impl<S: ByteStorage> GenericByteBuffer<S> {
+    /// A zero-filled buffer of `cap` bytes with the given limit.
+    pub fn new2(cap: i32, limit: i32) -> Self {
+        let buffer = ByteBuffer::new_(-1, 0, limit, cap);
+        Self {
+            buffer,
+            hb: S::from_vec(vec![0; cap as usize]),
+            offset: 0,
+        }
+    }
+
+    /// Copy `buf` into a new buffer with position `off`, limit `off + len` and
+    /// capacity `buf.len()`.
+    pub fn new3(buf: &[u8], off: i32, len: i32) -> Self {
+        let buffer = ByteBuffer::new_(-1, off, off + len, buf.len() as i32);
+        Self {
+            buffer,
+            hb: S::from_vec(buf.to_vec()),
+            offset: 0,
+        }
+    }
+
+    /// NIO `slice()` of the remaining bytes. Whether it shares storage with `self`
+    /// depends on `S`.
+    pub fn slice(&self) -> Self {
+        let buffer = ByteBuffer::new_(-1, 0, self.buffer.remaining(), self.buffer.remaining());
+        Self {
+            buffer,
+            hb: self.hb.share(),
+            offset: self.buffer.position() + self.offset,
+        }
+    }
+
+    pub fn ix(&self, i: i32) -> i32 {
+        i + self.offset
+    }
+
+    /// The bytes of `[0, limit)`, copied out.
+    pub fn to_vec(&self) -> Vec<u8> {
+        let from = self.ix(0) as usize;
+        let to = self.ix(self.limit()) as usize;
+        self.hb.with(|hb| hb[from..to].to_vec())
+    }
+
+    pub fn get(&mut self) -> u8 {
+        let idx = self.buffer.buffer.next_get_index();
+        let ix = self.ix(idx) as usize;
+        self.hb.with(|hb| hb[ix])
+    }
+
+    pub fn get_i(&mut self, i: i32) -> u8 {
+        let idx = self.buffer.buffer.check_index(i);
+        let ix = self.ix(idx) as usize;
+        self.hb.with(|hb| hb[ix])
+    }
+
+    pub fn put(&mut self, x: u8) {
+        self.buffer.check_writable();
+        let idx = self.buffer.buffer.next_put_index();
+        self.put_i(x, idx)
+    }
+
+    pub fn put_i(&mut self, x: u8, i: i32) {
+        self.buffer.check_writable();
+        let idx = self.buffer.buffer.check_index(i);
+        let ix = self.ix(idx) as usize;
+        self.hb.with_mut(|hb| hb[ix] = x);
+    }
+
+    fn get_array<const N: usize>(&mut self) -> [u8; N] {
+        let idx = self.buffer.buffer.next_get_index_nb(N as i32);
+        let ix = self.ix(idx) as usize;
+        let mut bytes = [0u8; N];
+        self.hb.with(|hb| bytes.copy_from_slice(&hb[ix..ix + N]));
+        bytes
+    }
+
+    fn put_bytes(&mut self, bytes: &[u8]) -> &mut Self {
+        self.buffer.check_writable();
+        let idx = self.buffer.buffer.next_put_index_nb(bytes.len() as i32);
+        let ix = self.ix(idx) as usize;
+        self.hb.with_mut(|hb| hb[ix..ix + bytes.len()].copy_from_slice(bytes));
+        self
+    }
+
+    // Multi-byte accessors are big-endian, like `CloneByteBuffer`'s defaults.
+
+    pub fn get_i16(&mut self) -> i16 {
+        i16::from_be_bytes(self.get_array())
+    }
+
+    pub fn get_i32(&mut self) -> i32 {
+        i32::from_be_bytes(self.get_array())
+    }
+
+    pub fn get_i64(&mut self) -> i64 {
+        i64::from_be_bytes(self.get_array())
+    }
+
+    pub fn get_f32(&mut self) -> f32 {
+        f32::from_be_bytes(self.get_array())
+    }
+
+    pub fn get_f64(&mut self) -> f64 {
+        f64::from_be_bytes(self.get_array())
+    }
+
+    pub fn put_i16(&mut self, x: i16) -> &mut Self {
+        self.put_bytes(&x.to_be_bytes())
+    }
+
+    pub fn put_i32(&mut self, x: i32) -> &mut Self {
+        self.put_bytes(&x.to_be_bytes())
+    }
+
+    pub fn put_i64(&mut self, x: i64) -> &mut Self {
+        self.put_bytes(&x.to_be_bytes())
+    }
+
+    pub fn put_f32(&mut self, x: f32) -> &mut Self {
+        self.put_bytes(&x.to_be_bytes())
+    }
+
+    pub fn put_f64(&mut self, x: f64) -> &mut Self {
+        self.put_bytes(&x.to_be_bytes())
+    }
+}
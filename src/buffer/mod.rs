@@ -1,3 +1,11 @@
 pub mod arc_bytebuffer;
+pub mod bits;
+pub mod borrowed_bytebuffer;
 pub mod buffer;
-pub mod clone_bytebuffer;
\ No newline at end of file
+pub mod byte_order;
+pub mod checksum;
+pub mod clone_bytebuffer;
+pub mod error;
+pub mod generic_bytebuffer;
+#[cfg(feature = "pod")]
+pub mod pod;
\ No newline at end of file
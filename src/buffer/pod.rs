@@ -0,0 +1,22 @@
+/// Plain old data: types for which every bit pattern is a valid value and which
+/// contain no padding, so they can be read straight out of a byte buffer.
+///
+/// ## Safety
+///
+/// Only implement this for `Copy` types without padding bytes, pointers or
+/// invalid bit patterns (so no `bool`, `char`, enums or references).
+pub unsafe trait Pod: Copy + 'static {}
+
+unsafe impl Pod for u8 {}
+unsafe impl Pod for i8 {}
+unsafe impl Pod for u16 {}
+unsafe impl Pod for i16 {}
+unsafe impl Pod for u32 {}
+unsafe impl Pod for i32 {}
+unsafe impl Pod for u64 {}
+unsafe impl Pod for i64 {}
+unsafe impl Pod for u128 {}
+unsafe impl Pod for i128 {}
+unsafe impl Pod for f32 {}
+unsafe impl Pod for f64 {}
+unsafe impl<T: Pod, const N: usize> Pod for [T; N] {}
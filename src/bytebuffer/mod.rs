@@ -29,8 +29,9 @@
 ///    (among many similar issues across many crates).
 ///
 /// Note that calling `destroy` manually is often not needed, as usually you should
-/// be passing these to the function defined by [`define_bytebuffer_destructor!`] from
-/// the other side of the FFI.
+/// be passing these to the function defined by
+/// [`define_bytebuffer_destructor!`](crate::define_bytebuffer_destructor) from the other
+/// side of the FFI.
 ///
 /// Because this type is essentially *only* useful in unsafe or FFI code (and because
 /// the most common usage pattern does not require manually managing the memory), it
@@ -79,7 +80,8 @@
 ///
 /// Typically, this object is managed on the other side of the FFI (on the "FFI consumer"), which
 /// means you must expose a function to release the resources of `data` which can be done easily
-/// using the [`define_bytebuffer_destructor!`] macro provided by this crate.
+/// using the [`define_bytebuffer_destructor!`](crate::define_bytebuffer_destructor) macro
+/// provided by this crate.
 #[repr(C)]
 pub struct ByteBuffer {
     len: i64,
@@ -123,12 +125,56 @@ impl ByteBuffer {
     /// This will panic if the buffer length (`usize`) cannot fit into a `i64`.
     #[inline]
     pub fn from_vec(bytes: Vec<u8>) -> Self {
+        Self::try_from_vec(bytes).expect("buffer length cannot fit into a i64.")
+    }
+
+    /// Like [`ByteBuffer::from_vec`], but returns
+    /// [`ByteBufferError::LengthOverflow`] instead of panicking if the buffer
+    /// length (`usize`) cannot fit into a `i64`. On error `bytes` is dropped,
+    /// not leaked.
+    #[inline]
+    pub fn try_from_vec(bytes: Vec<u8>) -> Result<Self, ByteBufferError> {
         use std::convert::TryFrom;
+        let len = i64::try_from(bytes.len())
+            .map_err(|_| ByteBufferError::LengthOverflow { len: bytes.len() })?;
         let mut buf = bytes.into_boxed_slice();
         let data = buf.as_mut_ptr();
-        let len = i64::try_from(buf.len()).expect("buffer length cannot fit into a i64.");
         std::mem::forget(buf);
-        Self { data, len }
+        Ok(Self { data, len })
+    }
+
+    /// Creates a `ByteBuffer` from its raw fields, e.g. ones received from the
+    /// other side of the FFI or built by custom glue code. Nothing is checked.
+    ///
+    /// ## Safety
+    ///
+    /// The result is only safe to read (`as_slice`, `as_mut_slice`, ...) if either
+    /// `data` is null, or `data` points to `len` initialized bytes that are valid
+    /// for reads and writes and not aliased elsewhere for the buffer's lifetime.
+    ///
+    /// It is only safe to free (`destroy`, `destroy_into_vec`) if, in addition,
+    /// `data` and `len` came from a boxed slice allocated by the current Rust
+    /// global allocator, i.e. from a `ByteBuffer` built by this crate's
+    /// constructors, and ownership was not already given away.
+    ///
+    /// A negative `len` is accepted here but makes `len()` and every accessor
+    /// that relies on it panic.
+    #[inline]
+    pub unsafe fn from_raw_parts(data: *mut u8, len: i64) -> Self {
+        Self { len, data }
+    }
+
+    /// The `data` pointer, which is null for a null buffer. Together with `len()`
+    /// this gives back what `from_raw_parts` takes.
+    #[inline]
+    pub fn as_ptr(&self) -> *const u8 {
+        self.data
+    }
+
+    /// Mutable counterpart of [`ByteBuffer::as_ptr`].
+    #[inline]
+    pub fn as_mut_ptr(&mut self) -> *mut u8 {
+        self.data
     }
 
     /// View the data inside this `ByteBuffer` as a `&[u8]`.
@@ -142,14 +188,38 @@ impl ByteBuffer {
         }
     }
 
+    /// View the data inside this `ByteBuffer` as a `&[u8]`, returning `None`
+    /// if `data` is null. Unlike [`ByteBuffer::as_slice`], this tells a null
+    /// buffer apart from an empty one.
     #[inline]
-    fn len(&self) -> usize {
+    pub fn try_as_slice(&self) -> Option<&[u8]> {
+        if self.data.is_null() {
+            None
+        } else {
+            Some(self.as_slice())
+        }
+    }
+
+    /// The number of bytes, read from the `len` field without touching `data`.
+    ///
+    /// ## Caveats
+    ///
+    /// Panics if `len` is negative, which only a malformed buffer from the other
+    /// side of the FFI can have.
+    #[inline]
+    pub fn len(&self) -> usize {
         use std::convert::TryInto;
         self.len
             .try_into()
             .expect("ByteBuffer length negative or overflowed")
     }
 
+    /// Whether there are no bytes to read: `len` is zero or `data` is null.
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.data.is_null() || self.len == 0
+    }
+
     /// View the data inside this `ByteBuffer` as a `&mut [u8]`.
     // TODO: Is it worth implementing `DerefMut`? Patches welcome if you need this.
     #[inline]
@@ -161,6 +231,27 @@ impl ByteBuffer {
         }
     }
 
+    /// Apply `f` to every byte of this `ByteBuffer` in place, e.g. to XOR-mask
+    /// a payload before handing it over the FFI. Does nothing if `data` is null.
+    #[inline]
+    pub fn map_in_place<F: FnMut(u8) -> u8>(&mut self, mut f: F) {
+        for b in self.as_mut_slice() {
+            *b = f(*b);
+        }
+    }
+
+    /// Iterate over the bytes of this `ByteBuffer`. Empty if `data` is null.
+    #[inline]
+    pub fn iter(&self) -> std::slice::Iter<'_, u8> {
+        self.as_slice().iter()
+    }
+
+    /// Iterate mutably over the bytes of this `ByteBuffer`. Empty if `data` is null.
+    #[inline]
+    pub fn iter_mut(&mut self) -> std::slice::IterMut<'_, u8> {
+        self.as_mut_slice().iter_mut()
+    }
+
     /// Deprecated alias for [`ByteBuffer::destroy_into_vec`].
     #[inline]
     #[deprecated = "Name is confusing, please use `destroy_into_vec` instead"]
@@ -208,7 +299,7 @@ impl ByteBuffer {
     /// Reclaim memory stored in this ByteBuffer.
     ///
     /// You typically should not call this manually, and instead expose a
-    /// function that does so via [`define_bytebuffer_destructor!`].
+    /// function that does so via [`define_bytebuffer_destructor!`](crate::define_bytebuffer_destructor).
     ///
     /// ## Caveats
     ///
@@ -245,6 +336,105 @@ impl Default for ByteBuffer {
     }
 }
 
+/// A deep copy: the clone gets its own allocation (via [`ByteBuffer::from_vec`])
+/// holding the same bytes, so both must be destroyed and neither sees the other's
+/// writes. A null buffer clones to a null buffer.
+impl Clone for ByteBuffer {
+    fn clone(&self) -> Self {
+        if self.data.is_null() {
+            return Self::default();
+        }
+        Self::from_vec(self.as_slice().to_vec())
+    }
+}
+
+/// A [`ByteBuffer`] that destroys itself when dropped, for Rust-only code that
+/// would otherwise have to remember to call [`ByteBuffer::destroy`]. Derefs to
+/// the bytes. Use [`OwnedByteBuffer::into_inner`] to get the raw buffer back,
+/// e.g. to hand it over the FFI.
+pub struct OwnedByteBuffer(ByteBuffer);
+
+impl OwnedByteBuffer {
+    /// See [`ByteBuffer::from_vec`].
+    #[inline]
+    pub fn from_vec(bytes: Vec<u8>) -> Self {
+        OwnedByteBuffer(ByteBuffer::from_vec(bytes))
+    }
+
+    /// Disarm the destructor and return the raw buffer, which must then be
+    /// destroyed (or handed over) by the caller.
+    #[inline]
+    pub fn into_inner(self) -> ByteBuffer {
+        let mut this = std::mem::ManuallyDrop::new(self);
+        std::mem::take(&mut this.0)
+    }
+}
+
+impl Drop for OwnedByteBuffer {
+    fn drop(&mut self) {
+        std::mem::take(&mut self.0).destroy();
+    }
+}
+
+impl std::ops::Deref for OwnedByteBuffer {
+    type Target = [u8];
+
+    #[inline]
+    fn deref(&self) -> &[u8] {
+        self.0.as_slice()
+    }
+}
+
+impl std::ops::DerefMut for OwnedByteBuffer {
+    #[inline]
+    fn deref_mut(&mut self) -> &mut [u8] {
+        self.0.as_mut_slice()
+    }
+}
+
+/// Errors returned by the fallible [`ByteBuffer`] constructors.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ByteBufferError {
+    /// The length does not fit into the `i64` length field.
+    LengthOverflow { len: usize },
+}
+
+impl std::fmt::Display for ByteBufferError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ByteBufferError::LengthOverflow { len } => {
+                write!(f, "buffer length {} cannot fit into a i64", len)
+            }
+        }
+    }
+}
+
+impl std::error::Error for ByteBufferError {}
+
+/// Define a (public) destructor for the [`ByteBuffer`] type, to be called from the
+/// other side of the FFI to release the memory it owns.
+///
+/// ## Caveats
+///
+/// The generated function is `#[no_mangle]`, so its name must be unique across
+/// everything linked into the final library; prefix it with your library's name.
+///
+/// ## Example
+///
+/// ```rust
+/// # use bytebuffers::define_bytebuffer_destructor;
+/// define_bytebuffer_destructor!(mylib_destroy_bytebuffer);
+/// ```
+#[macro_export]
+macro_rules! define_bytebuffer_destructor {
+    ($destructor_name:ident) => {
+        #[no_mangle]
+        pub extern "C" fn $destructor_name(v: $crate::bytebuffer::ByteBuffer) {
+            v.destroy()
+        }
+    };
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
@@ -270,6 +460,107 @@ mod test {
         assert_eq!(bb.destroy_into_vec(), &[]);
     }
 
+    #[test]
+    fn test_bb_map_in_place() {
+        let mut bb = ByteBuffer::from_vec(vec![0u8, 1, 2, 0xff]);
+        bb.map_in_place(|b| !b);
+        assert_eq!(bb.as_slice(), &[0xffu8, 0xfe, 0xfd, 0]);
+        bb.destroy();
+
+        let mut bb = ByteBuffer::default();
+        bb.map_in_place(|b| !b);
+        assert_eq!(bb.as_slice(), &[]);
+    }
+
+    #[test]
+    fn test_bb_try_as_slice() {
+        let bb = ByteBuffer::default();
+        assert_eq!(bb.try_as_slice(), None);
+
+        let bb = ByteBuffer::from_vec(vec![]);
+        assert_eq!(bb.try_as_slice(), Some(&[][..]));
+        bb.destroy();
+
+        let bb = ByteBuffer::from_vec(vec![1u8, 2]);
+        assert_eq!(bb.try_as_slice(), Some(&[1u8, 2][..]));
+        bb.destroy();
+    }
+
+    #[test]
+    fn test_bb_len() {
+        let bb = ByteBuffer::from_vec(vec![1u8, 2, 3]);
+        assert_eq!(bb.len(), 3);
+        assert!(!bb.is_empty());
+        bb.destroy();
+
+        let bb = ByteBuffer::from_vec(vec![]);
+        assert_eq!(bb.len(), 0);
+        assert!(bb.is_empty());
+        bb.destroy();
+
+        // null data counts as empty whatever `len` says
+        let bb = unsafe { ByteBuffer::from_raw_parts(std::ptr::null_mut(), 4) };
+        assert!(bb.is_empty());
+    }
+
+    #[test]
+    fn test_bb_from_raw_parts() {
+        let bb = ByteBuffer::from_vec(vec![1u8, 2, 3]);
+        let (data, len) = (bb.data, bb.len);
+        let bb = unsafe { ByteBuffer::from_raw_parts(data, len) };
+        assert_eq!(bb.as_slice(), &[1u8, 2, 3]);
+        bb.destroy();
+    }
+
+    #[test]
+    fn test_bb_ptr_round_trip() {
+        let data = Box::into_raw(vec![4u8, 5, 6].into_boxed_slice()) as *mut u8;
+        let mut bb = unsafe { ByteBuffer::from_raw_parts(data, 3) };
+        assert_eq!(bb.as_ptr(), data as *const u8);
+        unsafe { *bb.as_mut_ptr().add(1) = 9 };
+        let bb = unsafe { ByteBuffer::from_raw_parts(bb.as_mut_ptr(), bb.len() as i64) };
+        assert_eq!(bb.destroy_into_vec(), vec![4u8, 9, 6]);
+
+        assert!(ByteBuffer::default().as_ptr().is_null());
+    }
+
+    #[test]
+    #[should_panic(expected = "ByteBuffer length negative or overflowed")]
+    fn test_bb_from_raw_parts_negative_len() {
+        let mut byte = 0u8;
+        let bb = unsafe { ByteBuffer::from_raw_parts(&mut byte, -1) };
+        bb.len();
+    }
+
+    #[test]
+    #[should_panic(expected = "ByteBuffer length negative or overflowed")]
+    fn test_bb_from_raw_parts_negative_len_destroy() {
+        let mut byte = 0u8;
+        let bb = unsafe { ByteBuffer::from_raw_parts(&mut byte, -1) };
+        // panics before anything is freed
+        bb.destroy_into_vec();
+    }
+
+    #[test]
+    fn test_bb_try_from_vec() {
+        let bb = ByteBuffer::try_from_vec(vec![1u8, 2, 3]).unwrap();
+        assert_eq!(bb.as_slice(), &[1u8, 2, 3]);
+        bb.destroy();
+
+        // a `Vec` longer than `i64::MAX` can't be built, so only check the error itself
+        let err = ByteBufferError::LengthOverflow { len: usize::MAX };
+        assert_eq!(err.to_string(), format!("buffer length {} cannot fit into a i64", usize::MAX));
+    }
+
+    define_bytebuffer_destructor!(bytebuffers_test_destroy_bytebuffer);
+
+    #[test]
+    fn test_bb_destructor_macro() {
+        let destroy: extern "C" fn(ByteBuffer) = bytebuffers_test_destroy_bytebuffer;
+        destroy(ByteBuffer::from_vec(vec![1u8, 2, 3]));
+        destroy(ByteBuffer::default());
+    }
+
     #[test]
     fn test_bb_new() {
         let bb = ByteBuffer::new_with_size(5);
@@ -286,4 +577,49 @@ mod test {
         assert!(!bb.data.is_null());
         bb.destroy();
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_bb_iter() {
+        let mut bb = ByteBuffer::from_vec(vec![1u8, 2, 3]);
+        for b in bb.iter_mut() {
+            *b *= 2;
+        }
+        assert_eq!(bb.iter().copied().collect::<Vec<u8>>(), vec![2, 4, 6]);
+        bb.destroy();
+
+        let mut bb = ByteBuffer::default();
+        assert!(bb.data.is_null());
+        assert_eq!(bb.iter().count(), 0);
+        assert_eq!(bb.iter_mut().count(), 0);
+    }
+
+    #[test]
+    fn test_bb_clone() {
+        let mut bb = ByteBuffer::from_vec(vec![1u8, 2, 3]);
+        let copy = bb.clone();
+        assert_ne!(copy.as_ptr(), bb.as_ptr());
+        bb.as_mut_slice()[0] = 9;
+        assert_eq!(copy.as_slice(), &[1u8, 2, 3]);
+        assert_eq!(bb.as_slice(), &[9u8, 2, 3]);
+        bb.destroy();
+        copy.destroy();
+
+        assert!(ByteBuffer::default().clone().as_ptr().is_null());
+    }
+
+    #[test]
+    fn test_bb_owned() {
+        let mut owned = OwnedByteBuffer::from_vec(vec![1u8, 2, 3]);
+        owned[0] = 7;
+        assert_eq!(&owned[..], &[7u8, 2, 3]);
+        assert_eq!(owned.len(), 3);
+        drop(owned);
+
+        // into_inner disarms the destructor: the bytes are still there to read and free
+        let owned = OwnedByteBuffer::from_vec(vec![4u8, 5]);
+        let ptr = owned.as_ptr();
+        let bb = owned.into_inner();
+        assert_eq!(bb.as_ptr(), ptr);
+        assert_eq!(bb.destroy_into_vec(), vec![4u8, 5]);
+    }
+}
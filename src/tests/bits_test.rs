@@ -0,0 +1,77 @@
+use crate::buffer::bits::{BitReader, BitWriter};
+use crate::buffer::buffer::IBuffer;
+use crate::buffer::clone_bytebuffer::CloneByteBuffer;
+
+#[test]
+fn test_bit_reader() {
+    // 101 | 10011
+    let buffer = CloneByteBuffer::new3(&[0b1011_0011], 0, 1);
+    let mut reader = BitReader::new(buffer);
+    assert_eq!(reader.get_bits(3), 0b101);
+    assert_eq!(reader.get_bits(5), 0b10011);
+    assert!(!reader.buffer.has_remaining());
+}
+
+#[test]
+fn test_bit_reader_across_bytes() {
+    let buffer = CloneByteBuffer::new3(&[0xab, 0xcd, 0xef, 0x01, 0x23, 0x45, 0x67, 0x89, 0xff], 0, 9);
+    let mut reader = BitReader::new(buffer);
+    assert_eq!(reader.get_bits(4), 0xa);
+    assert_eq!(reader.get_bits(12), 0xbcd);
+    assert_eq!(reader.get_bits(1), 1);
+    let mut reader = BitReader::new(reader.into_inner());
+    reader.buffer.position_(0);
+    assert_eq!(reader.get_bits(64), 0xabcd_ef01_2345_6789);
+}
+
+#[test]
+#[should_panic(expected = "buffer under flow!")]
+fn test_bit_reader_under_flow() {
+    let buffer = CloneByteBuffer::new3(&[0xff], 0, 1);
+    let mut reader = BitReader::new(buffer);
+    reader.get_bits(9);
+}
+
+#[test]
+fn test_bit_writer_round_trip() {
+    let fields = [(0b1u64, 1), (0b101, 3), (0x1ff, 9), (0, 2), (0x12345, 17), (u64::MAX, 64), (0b11, 2)];
+    let mut writer = BitWriter::new(CloneByteBuffer::new2(1, 1));
+    for &(value, n) in fields.iter() {
+        writer.put_bits(value, n);
+    }
+    writer.finish();
+    let mut buffer = writer.into_inner();
+    // 98 bits -> 13 bytes, the last one zero padded
+    assert_eq!(buffer.position(), 13);
+    buffer.flip();
+
+    let mut reader = BitReader::new(buffer);
+    for &(value, n) in fields.iter() {
+        assert_eq!(reader.get_bits(n), value);
+    }
+    assert_eq!(reader.get_bits(6), 0);
+    assert!(!reader.buffer.has_remaining());
+}
+
+#[test]
+fn test_bit_writer_padding() {
+    let mut writer = BitWriter::new(CloneByteBuffer::new2(4, 4));
+    writer.put_bits(0b101, 3);
+    writer.finish();
+    let buffer = writer.into_inner();
+    assert_eq!(buffer.position(), 1);
+    assert_eq!(buffer.hb.borrow()[0], 0b1010_0000);
+}
+
+#[test]
+fn test_bit_writer_limit_below_cap() {
+    let mut writer = BitWriter::new(CloneByteBuffer::new2(4, 1));
+    writer.put_bits(0xff, 8);
+    writer.put_bits(0b1, 1);
+    writer.finish();
+    let buffer = writer.into_inner();
+    assert_eq!(buffer.position(), 2);
+    assert_eq!(buffer.limit(), 4);
+    assert_eq!(buffer.cap(), 4);
+    assert_eq!(&buffer.hb.borrow()[..2], &[0xff, 0b1000_0000]);
+}
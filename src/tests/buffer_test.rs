@@ -1,5 +1,4 @@
 use std::ops::Range;
-use std::cell::RefCell;
 use crate::buffer::buffer::{Buffer, IBuffer, ByteBuffer};
 use crate::buffer::clone_bytebuffer::CloneByteBuffer;
 use crate::buffer::arc_bytebuffer::ArcByteBuffer;
@@ -64,7 +63,7 @@ fn test_buffer_slice() {
     // println!("buffer  puts {:?}", &buffer);
     assert_eq!(buffer.position(), 5);
     assert_eq!(buffer.offset, 0);
-    assert_eq!(buffer.hb, RefCell::new(vec![0, 1, 2, 3, 4, 0, 0, 0, 0, 0]));
+    assert_eq!(*buffer.hb.borrow(), vec![0, 1, 2, 3, 4, 0, 0, 0, 0, 0]);
 
     let mut slice = buffer.slice();
     // println!("buffer slice {:?}", &slice);
@@ -72,7 +71,7 @@ fn test_buffer_slice() {
     assert_eq!(slice.offset, 5);
     assert_eq!(slice.limit(), 5);
     assert_eq!(slice.cap(), 5);
-    assert_eq!(slice.hb, RefCell::new(vec![0, 1, 2, 3, 4, 0, 0, 0, 0, 0]));
+    assert_eq!(*slice.hb.borrow(), vec![0, 1, 2, 3, 4, 0, 0, 0, 0, 0]);
 
     // slice.flip();
     for i in 10..12 {
@@ -81,7 +80,10 @@ fn test_buffer_slice() {
     // println!("slice   puts {:?}", &slice);
     assert_eq!(slice.position(), 2);
     assert_eq!(slice.offset, 5);
-    assert_eq!(slice.hb, RefCell::new(vec![0, 1, 2, 3, 4, 10, 11, 0, 0, 0]));
+    assert_eq!(*slice.hb.borrow(), vec![0, 1, 2, 3, 4, 10, 11, 0, 0, 0]);
+    // the slice shares the parent's storage
+    assert_eq!(*buffer.hb.borrow(), vec![0, 1, 2, 3, 4, 10, 11, 0, 0, 0]);
+    assert_eq!(buffer.get_i(6), 11);
 
     for i in 20..23 {
         buffer.put(i);
@@ -89,7 +91,7 @@ fn test_buffer_slice() {
     // println!("buffer ##### {:?}", &buffer);
     assert_eq!(buffer.position(), 8);
     assert_eq!(buffer.offset, 0);
-    assert_eq!(buffer.hb, RefCell::new(vec![0, 1, 2, 3, 4, 20, 21, 22, 0, 0]));
+    assert_eq!(*buffer.hb.borrow(), vec![0, 1, 2, 3, 4, 20, 21, 22, 0, 0]);
 }
 
 #[test]
@@ -101,7 +103,7 @@ fn test_buffer_slice_refcell() {
     let mut slice = buffer.slice();
     assert_eq!(buffer.position(), 5);
     assert_eq!(slice.position(), 0);
-    assert_eq!(slice.hb, RefCell::new(vec![0, 1, 2, 3, 4, 0, 0, 0, 0, 0]));
+    assert_eq!(*slice.hb.borrow(), vec![0, 1, 2, 3, 4, 0, 0, 0, 0, 0]);
 
     // let s1 = slice.get();
     // assert_eq!(buffer.position(), 5);
@@ -122,6 +124,14 @@ fn test_buffer_slice_refcell() {
 
     slice.put(10);
     slice.put(11);
+    assert_eq!(buffer.get_i(5), 10);
+    assert_eq!(buffer.get_i(6), 11);
+    assert_eq!(buffer.position(), 5);
+
+    // and the other way round, at the translated offset
+    buffer.put_i(12, 7);
+    slice.flip().limit_(3);
+    assert_eq!(slice.get_i(2), 12);
 
     // slice.flip();
     // let s1 = slice.get();
@@ -206,7 +216,7 @@ fn test_buffer_get_put() {
     buffer.put_buf(&mut v, 0, 5);
     assert_eq!(buffer.position(), 5);
     assert_eq!(buffer.offset, 0);
-    assert_eq!(buffer.hb, RefCell::new(v));
+    assert_eq!(*buffer.hb.borrow(), v);
 
     // put_buffer: 将源HeapByteBuffer的内容，放入当前的HeapByteBuffer中
 
@@ -245,4 +255,2151 @@ fn test_arc() {
     // Now `data` and `other_data` point to different allocations.
     assert_eq!(*data, 8);
     assert_eq!(*other_data, 12);
-}
\ No newline at end of file
+}
+
+#[test]
+fn test_buffer_structural_eq() {
+    let mut a = CloneByteBuffer::new2(5, 5);
+    let mut b = CloneByteBuffer::new2(5, 5);
+    for i in 0..5 {
+        a.put(i);
+        b.put(i);
+    }
+    assert!(a.structural_eq(&b));
+
+    // same remaining content [2, 3, 4], but different position and backing store
+    a.flip();
+    a.position_(2);
+    let mut c = CloneByteBuffer::new2(3, 3);
+    for i in 2..5 {
+        c.put(i);
+    }
+    c.flip();
+    let mut ra = vec![0; 3];
+    let mut rc = vec![0; 3];
+    a.clone().get_buf(&mut ra, 0, 3);
+    c.clone().get_buf(&mut rc, 0, 3);
+    assert_eq!(ra, rc);
+    assert!(!a.structural_eq(&c));
+
+    b.flip();
+    assert!(!a.structural_eq(&b));
+    b.position_(2);
+    assert!(a.structural_eq(&b));
+}
+
+#[test]
+fn test_buffer_swap_bytes() {
+    let data = [1, 2, 3, 4, 5, 6, 7, 8];
+    let mut buffer = CloneByteBuffer::new3(&data, 0, 8);
+    buffer.swap_bytes_u16();
+    assert_eq!(buffer.position(), 0);
+    assert_eq!(*buffer.hb.borrow(), vec![2, 1, 4, 3, 6, 5, 8, 7]);
+
+    let mut buffer = CloneByteBuffer::new3(&data, 0, 8);
+    buffer.swap_bytes_u32();
+    assert_eq!(*buffer.hb.borrow(), vec![4, 3, 2, 1, 8, 7, 6, 5]);
+
+    let mut buffer = CloneByteBuffer::new3(&data, 0, 8);
+    buffer.swap_bytes_u64();
+    assert_eq!(*buffer.hb.borrow(), vec![8, 7, 6, 5, 4, 3, 2, 1]);
+
+    // only the remaining region is touched
+    let mut buffer = CloneByteBuffer::new3(&data, 4, 4);
+    buffer.swap_bytes_u32();
+    assert_eq!(*buffer.hb.borrow(), vec![1, 2, 3, 4, 8, 7, 6, 5]);
+}
+
+#[test]
+#[should_panic(expected = "remaining is not a multiple of 4")]
+fn test_buffer_swap_bytes_width() {
+    let mut buffer = CloneByteBuffer::new3(&[1, 2, 3, 4, 5, 6], 0, 6);
+    buffer.swap_bytes_u32();
+}
+
+#[cfg(feature = "trace")]
+#[test]
+fn test_buffer_trace() {
+    use std::cell::Cell;
+    use std::rc::Rc;
+
+    let events = Rc::new(Cell::new(0));
+    let moves = Rc::clone(&events);
+    let mut buffer = CloneByteBuffer::new2(10, 10);
+    buffer.set_trace(move |old, new| {
+        assert_ne!(old, new);
+        moves.set(moves.get() + 1);
+    });
+    for i in 0..3 {
+        buffer.put(i);
+    }
+    assert_eq!(events.get(), 3);
+    buffer.flip();
+    assert_eq!(events.get(), 4);
+    buffer.get();
+    buffer.position_(3);
+    assert_eq!(events.get(), 6);
+    // no move, no event
+    buffer.position_(3);
+    assert_eq!(events.get(), 6);
+}
+
+#[test]
+fn test_buffer_limit_clamped() {
+    let mut buffer = Buffer::new_(-1, 0, 10, 10);
+    buffer.limit_clamped(20);
+    assert_eq!(buffer.limit(), 10);
+    buffer.limit_clamped(-3);
+    assert_eq!(buffer.limit(), 0);
+
+    let mut buffer = CloneByteBuffer::new2(10, 10);
+    buffer.position_(8);
+    buffer.mark_();
+    buffer.limit_clamped(100);
+    assert_eq!(buffer.limit(), 10);
+    assert_eq!(buffer.position(), 8);
+    assert_eq!(buffer.mark(), 8);
+    buffer.limit_clamped(-1);
+    assert_eq!(buffer.limit(), 0);
+    assert_eq!(buffer.position(), 0);
+    assert_eq!(buffer.mark(), -1);
+}
+
+#[test]
+fn test_buffer_append_buffer() {
+    let mut frames = CloneByteBuffer::new2(0, 0);
+    let mut a = CloneByteBuffer::new2(3, 3);
+    for i in 1..4 {
+        a.put(i);
+    }
+    let b = CloneByteBuffer::new3(&[4, 5], 1, 1);
+
+    frames.append_buffer(&a);
+    assert_eq!(frames.position(), 0);
+    assert_eq!(frames.cap(), 3);
+    frames.append_buffer(&b);
+    assert_eq!(frames.position(), 0);
+    assert_eq!(frames.cap(), 5);
+    assert_eq!(frames.limit(), 5);
+    assert_eq!(*frames.hb.borrow(), vec![1, 2, 3, 4, 5]);
+}
+
+#[test]
+fn test_buffer_fill_from() {
+    use std::io::Cursor;
+
+    fn load<B: IBuffer>(b: &mut B, src: &mut impl std::io::Read) -> i32 {
+        b.fill_from(src).unwrap()
+    }
+
+    // bulk override: stops when the buffer is full
+    let mut src = Cursor::new(vec![1, 2, 3, 4, 5, 6]);
+    let mut buffer = CloneByteBuffer::new2(4, 4);
+    assert_eq!(load(&mut buffer, &mut src), 4);
+    assert_eq!(buffer.position(), 4);
+    assert_eq!(*buffer.hb.borrow(), vec![1, 2, 3, 4]);
+
+    // default implementation: stops at EOF
+    let mut buffer = ArcByteBuffer::new2(4, 4);
+    assert_eq!(load(&mut buffer, &mut src), 2);
+    assert_eq!(buffer.position(), 2);
+    assert_eq!(*buffer.hb.read().unwrap(), vec![5, 6, 0, 0]);
+}
+
+#[test]
+fn test_buffer_discard_consumed() {
+    let mut buffer = CloneByteBuffer::new2(8, 8);
+    for i in 1..6 {
+        buffer.put(i);
+    }
+    buffer.flip();
+    for i in 1..4 {
+        assert_eq!(buffer.get(), i);
+    }
+    buffer.discard_consumed();
+    assert_eq!(buffer.position(), 0);
+    assert_eq!(buffer.limit(), 2);
+    assert_eq!(buffer.cap(), 8);
+    assert_eq!(buffer.get(), 4);
+    assert_eq!(buffer.get(), 5);
+    assert!(!buffer.has_remaining());
+}
+
+#[test]
+fn test_buffer_get_buf_checksummed() {
+    use crate::buffer::checksum::crc32;
+
+    let data = b"hello, checksum";
+    let mut buffer = CloneByteBuffer::new3(data, 0, data.len() as i32);
+    buffer.get();
+    let mut dst = [0u8; 10];
+    let crc = buffer.get_buf_checksummed(&mut dst);
+    assert_eq!(&dst, &data[1..11]);
+    assert_eq!(crc, crc32(&data[1..11]));
+    assert_eq!(buffer.position(), 11);
+}
+
+#[test]
+fn test_buffer_clear_keep_mark() {
+    let mut buffer = CloneByteBuffer::new2(10, 10);
+    buffer.position_(3);
+    buffer.mark_();
+    buffer.position_(6);
+    buffer.flip();
+    assert_eq!(buffer.mark(), -1);
+
+    buffer.clear();
+    buffer.position_(3);
+    buffer.mark_();
+    buffer.limit_(5);
+    buffer.clear_keep_mark();
+    assert_eq!(buffer.position(), 0);
+    assert_eq!(buffer.limit(), 10);
+    assert_eq!(buffer.mark(), 3);
+    buffer.position_(5);
+    buffer.reset();
+    assert_eq!(buffer.position(), 3);
+}
+
+#[test]
+fn test_buffer_slice_to_vec() {
+    let data: Vec<u8> = (0..10).collect();
+    let mut buffer = CloneByteBuffer::new3(&data, 0, 10);
+    buffer.position_(7);
+    assert_eq!(buffer.slice_to_vec(2, 5), vec![2, 3, 4]);
+    assert_eq!(buffer.position(), 7);
+    assert_eq!(buffer.slice_to_vec(4, 4), vec![]);
+
+    // absolute indexes are relative to the slice's offset
+    buffer.position_(5);
+    let slice = buffer.slice();
+    assert_eq!(slice.slice_to_vec(0, 2), vec![5, 6]);
+}
+
+#[test]
+#[should_panic(expected = "index out of bound")]
+fn test_buffer_slice_to_vec_bounds() {
+    let buffer = CloneByteBuffer::new2(10, 10);
+    buffer.slice_to_vec(2, 11);
+}
+
+#[test]
+fn test_buffer_put_iter() {
+    let mut buffer = CloneByteBuffer::new2(10, 10);
+    let mut iter = 0u8..100;
+    assert_eq!(buffer.put_iter(&mut iter), 10);
+    assert_eq!(buffer.position(), 10);
+    assert_eq!(*buffer.hb.borrow(), (0..10).collect::<Vec<u8>>());
+    assert_eq!(iter.next(), Some(10));
+
+    let mut buffer = CloneByteBuffer::new2(10, 10);
+    assert_eq!(buffer.put_iter(vec![7u8, 8, 9].into_iter()), 3);
+    assert_eq!(buffer.position(), 3);
+}
+
+#[test]
+fn test_buffer_flip_to() {
+    let mut buffer = CloneByteBuffer::new2(10, 10);
+    for i in 0..8 {
+        buffer.put(i);
+    }
+    buffer.mark_();
+    buffer.flip_to(5);
+    assert_eq!(buffer.position(), 0);
+    assert_eq!(buffer.limit(), 5);
+    assert_eq!(buffer.mark(), -1);
+    assert_eq!(buffer.remaining(), 5);
+}
+
+#[test]
+#[should_panic(expected = "illegal argument!")]
+fn test_buffer_flip_to_over_cap() {
+    let mut buffer = CloneByteBuffer::new2(10, 10);
+    buffer.flip_to(11);
+}
+
+#[test]
+fn test_buffer_get_guid_le() {
+    // {00112233-4455-6677-8899-aabbccddeeff} as stored by Windows
+    let guid = [
+        0x33, 0x22, 0x11, 0x00, 0x55, 0x44, 0x77, 0x66,
+        0x88, 0x99, 0xaa, 0xbb, 0xcc, 0xdd, 0xee, 0xff,
+    ];
+    let uuid = [
+        0x00, 0x11, 0x22, 0x33, 0x44, 0x55, 0x66, 0x77,
+        0x88, 0x99, 0xaa, 0xbb, 0xcc, 0xdd, 0xee, 0xff,
+    ];
+    let mut buffer = CloneByteBuffer::new3(&guid, 0, 16);
+    assert_eq!(buffer.get_guid_le(), uuid);
+    assert_eq!(buffer.position(), 16);
+}
+
+#[test]
+fn test_buffer_seek() {
+    use std::io::SeekFrom;
+
+    let mut buffer = CloneByteBuffer::new2(10, 8);
+    assert_eq!(buffer.seek(SeekFrom::Start(4)), 4);
+    assert_eq!(buffer.seek(SeekFrom::Current(3)), 7);
+    assert_eq!(buffer.seek(SeekFrom::Current(-5)), 2);
+    assert_eq!(buffer.seek(SeekFrom::End(0)), 8);
+    assert_eq!(buffer.seek(SeekFrom::End(-8)), 0);
+    assert_eq!(buffer.position(), 0);
+}
+
+#[test]
+#[should_panic(expected = "illegal argument!")]
+fn test_buffer_seek_past_limit() {
+    use std::io::SeekFrom;
+
+    let mut buffer = CloneByteBuffer::new2(10, 8);
+    buffer.seek(SeekFrom::End(1));
+}
+
+#[test]
+fn test_buffer_seek_extreme_delta() {
+    use std::io::SeekFrom;
+    use std::panic::{catch_unwind, AssertUnwindSafe};
+
+    let mut buffer = CloneByteBuffer::new2(10, 8);
+    buffer.position_(4);
+    let extremes = [
+        SeekFrom::Current(i64::MAX),
+        SeekFrom::End(i64::MAX),
+        SeekFrom::Current(i64::MIN),
+        SeekFrom::Start(u64::MAX),
+    ];
+    for from in extremes {
+        let err = catch_unwind(AssertUnwindSafe(|| buffer.seek(from))).unwrap_err();
+        assert_eq!(err.downcast_ref::<&str>(), Some(&"illegal argument!"));
+    }
+    assert_eq!(buffer.position(), 4);
+}
+
+#[test]
+fn test_buffer_get_cstr() {
+    use crate::buffer::error::BufferError;
+
+    let mut buffer = CloneByteBuffer::new3(b"abc\0d\xe9f\0gh", 0, 10);
+    assert_eq!(buffer.get_cstr(), Ok("abc".to_string()));
+    assert_eq!(buffer.position(), 4);
+
+    // invalid utf-8 does not advance
+    assert!(matches!(buffer.get_cstr(), Err(BufferError::InvalidUtf8(_))));
+    assert_eq!(buffer.position(), 4);
+
+    // unterminated does not advance
+    buffer.position_(8);
+    assert_eq!(buffer.get_cstr(), Err(BufferError::Underflow));
+    assert_eq!(buffer.position(), 8);
+}
+
+#[test]
+fn test_buffer_put_cstr() {
+    let mut buffer = CloneByteBuffer::new2(16, 16);
+    buffer.put_cstr("héllo");
+    buffer.put_cstr("");
+    assert_eq!(buffer.position(), 8);
+    buffer.flip();
+    assert_eq!(buffer.get_cstr(), Ok("héllo".to_string()));
+    assert_eq!(buffer.get_cstr(), Ok("".to_string()));
+    assert!(!buffer.has_remaining());
+}
+
+#[test]
+#[should_panic(expected = "string contains an interior null byte")]
+fn test_buffer_put_cstr_interior_null() {
+    let mut buffer = CloneByteBuffer::new2(16, 16);
+    buffer.put_cstr("a\0b");
+}
+
+#[test]
+fn test_buffer_fold_bytes() {
+    let mut buffer = CloneByteBuffer::new3(&[200, 100, 50, 7], 0, 4);
+    assert_eq!(buffer.fold_bytes(0u32, |acc, b| acc + b as u32), 357);
+    buffer.get();
+    assert_eq!(buffer.fold_bytes(0u8, |acc, b| acc.max(b)), 100);
+    assert_eq!(buffer.position(), 1);
+}
+
+#[test]
+fn test_buffer_try_get_put() {
+    use crate::buffer::error::BufferError;
+
+    let mut buffer = CloneByteBuffer::new2(26, 26);
+    buffer.try_put_i16(-2).unwrap();
+    buffer.try_put_i32(0x0102_0304).unwrap();
+    buffer.try_put_i64(i64::MIN).unwrap();
+    buffer.try_put_f32(1.5).unwrap();
+    buffer.try_put_f64(-0.25).unwrap();
+    assert_eq!(buffer.try_put_i16(1), Err(BufferError::Overflow));
+    assert_eq!(buffer.position(), 26);
+    assert_eq!(buffer.hb.borrow()[2..6], [1, 2, 3, 4]);
+
+    buffer.flip();
+    assert_eq!(buffer.try_get_i16(), Ok(-2));
+    assert_eq!(buffer.try_get_i32(), Ok(0x0102_0304));
+    assert_eq!(buffer.try_get_i64(), Ok(i64::MIN));
+    assert_eq!(buffer.try_get_f32(), Ok(1.5));
+    assert_eq!(buffer.try_get_f64(), Ok(-0.25));
+}
+
+#[test]
+fn test_buffer_try_get_truncated() {
+    use crate::buffer::error::BufferError;
+
+    fn decode(buffer: &mut CloneByteBuffer) -> Result<(i16, i64), BufferError> {
+        let a = buffer.try_get_i16()?;
+        let b = buffer.try_get_i64()?;
+        Ok((a, b))
+    }
+
+    let mut buffer = CloneByteBuffer::new3(&[0, 1, 0, 0, 0, 0, 0, 0, 0], 0, 9);
+    assert_eq!(decode(&mut buffer), Err(BufferError::Underflow));
+    // the i16 was consumed, the truncated i64 was not
+    assert_eq!(buffer.position(), 2);
+    assert_eq!(buffer.try_get_i32(), Ok(0));
+    assert_eq!(buffer.try_get_i32(), Err(BufferError::Underflow));
+    assert_eq!(buffer.position(), 6);
+}
+
+#[test]
+fn test_buffer_as_ptr() {
+    let mut buffer = CloneByteBuffer::new3(&[1, 2, 3, 4, 5], 0, 5);
+    buffer.position_(2);
+    let n = buffer.remaining() as usize;
+    let remaining = unsafe { std::slice::from_raw_parts(buffer.as_ptr(), n) };
+    assert_eq!(remaining, &[3, 4, 5]);
+
+    let ptr = buffer.as_mut_ptr();
+    unsafe {
+        *ptr = 9;
+    }
+    assert_eq!(buffer.get(), 9);
+}
+
+#[test]
+fn test_buffer_next_frame() {
+    let data = [0, 0, 0, 3, 7, 8, 9, 0, 0, 0, 0, 0, 0];
+    let mut buffer = CloneByteBuffer::new3(&data, 0, 13);
+    assert_eq!(buffer.next_frame(), Ok(Some(vec![7, 8, 9])));
+    assert_eq!(buffer.position(), 7);
+    assert_eq!(buffer.next_frame(), Ok(Some(vec![])));
+    assert_eq!(buffer.position(), 11);
+
+    // partial length prefix
+    assert_eq!(buffer.next_frame(), Ok(None));
+    assert_eq!(buffer.position(), 11);
+
+    // partial body
+    let mut buffer = CloneByteBuffer::new3(&[0, 0, 0, 5, 1, 2], 0, 6);
+    assert_eq!(buffer.next_frame(), Ok(None));
+    assert_eq!(buffer.position(), 0);
+}
+
+#[test]
+fn test_buffer_next_frame_budget() {
+    use crate::buffer::error::BufferError;
+    let mut buffer = CloneByteBuffer::new3(&[0, 0, 0, 2, 1, 2, 0, 0, 0, 1, 5], 0, 11);
+    buffer.set_read_budget(7);
+    // retrying a partial frame costs nothing
+    buffer.limit_(5);
+    for _ in 0..10 {
+        assert_eq!(buffer.next_frame(), Ok(None));
+    }
+    assert_eq!(buffer.read_budget(), Some(7));
+
+    buffer.limit_(11);
+    assert_eq!(buffer.next_frame(), Ok(Some(vec![1, 2])));
+    assert_eq!(buffer.read_budget(), Some(1));
+    assert_eq!(buffer.next_frame(), Err(BufferError::BudgetExceeded));
+    assert_eq!(buffer.position(), 6);
+}
+
+#[test]
+fn test_buffer_put_frame() {
+    let frames: Vec<Vec<u8>> = vec![vec![1, 2, 3], vec![], vec![4; 10]];
+    let mut buffer = CloneByteBuffer::new2(32, 32);
+    for frame in frames.iter() {
+        buffer.put_frame(frame);
+    }
+    assert_eq!(buffer.position(), 25);
+    buffer.flip();
+    for frame in frames.iter() {
+        assert_eq!(buffer.next_frame().unwrap().as_ref(), Some(frame));
+    }
+    assert_eq!(buffer.next_frame(), Ok(None));
+}
+
+#[test]
+#[should_panic(expected = "buffer over flow!")]
+fn test_buffer_put_frame_over_flow() {
+    // the limit is below the capacity, so the buffer doesn't grow
+    let mut buffer = CloneByteBuffer::new2(8, 6);
+    buffer.put_frame(&[1, 2, 3]);
+}
+
+#[test]
+fn test_buffer_put_frame_grow() {
+    let mut buffer = CloneByteBuffer::new2(6, 6);
+    buffer.put_frame(&[1]);
+    buffer.put_frame(&[2, 3, 4]);
+    assert_eq!((buffer.position(), buffer.limit(), buffer.cap()), (12, 12, 12));
+    buffer.flip();
+    assert_eq!(buffer.next_frame(), Ok(Some(vec![1])));
+    assert_eq!(buffer.next_frame(), Ok(Some(vec![2, 3, 4])));
+}
+
+#[test]
+fn test_buffer_try_get_put_trait() {
+    use crate::buffer::error::BufferError;
+
+    fn drain<B: IBuffer>(b: &mut B) -> Result<(), BufferError> {
+        loop {
+            b.try_get()?;
+        }
+    }
+
+    fn fill<B: IBuffer>(b: &mut B) -> Result<(), BufferError> {
+        let mut x = 1;
+        loop {
+            b.try_put(x)?;
+            x += 1;
+        }
+    }
+
+    let mut buffer = CloneByteBuffer::new2(3, 3);
+    assert_eq!(fill(&mut buffer), Err(BufferError::Overflow));
+    buffer.flip();
+    assert_eq!(drain(&mut buffer), Err(BufferError::Underflow));
+    assert_eq!(buffer.position(), 3);
+
+    // default implementation
+    let mut buffer = ArcByteBuffer::new2(3, 3);
+    assert_eq!(fill(&mut buffer), Err(BufferError::Overflow));
+    buffer.flip();
+    assert_eq!(IBuffer::try_get(&mut buffer), Ok(1));
+    assert_eq!(drain(&mut buffer), Err(BufferError::Underflow));
+    assert_eq!(buffer.position(), 3);
+}
+
+#[test]
+fn test_buffer_checkpoint() {
+    use crate::buffer::error::BufferError;
+
+    fn parse(buffer: &mut CloneByteBuffer) -> Result<(i16, i32), BufferError> {
+        let mut guard = buffer.checkpoint();
+        let a = guard.try_get_i16()?;
+        let b = guard.try_get_i32()?;
+        guard.commit();
+        Ok((a, b))
+    }
+
+    // dropped without commit: position restored
+    let mut buffer = CloneByteBuffer::new3(&[0, 1, 0, 0, 0], 0, 5);
+    assert_eq!(parse(&mut buffer), Err(BufferError::Underflow));
+    assert_eq!(buffer.position(), 0);
+
+    // committed: position kept
+    let mut buffer = CloneByteBuffer::new3(&[0, 1, 0, 0, 0, 2], 0, 6);
+    assert_eq!(parse(&mut buffer), Ok((1, 2)));
+    assert_eq!(buffer.position(), 6);
+
+    // limit and mark are restored too
+    let mut buffer = CloneByteBuffer::new2(10, 10);
+    buffer.position_(2).mark_();
+    {
+        let mut guard = buffer.checkpoint();
+        guard.limit_(1);
+        assert_eq!(guard.mark(), -1);
+    }
+    assert_eq!(buffer.limit(), 10);
+    assert_eq!(buffer.position(), 2);
+    assert_eq!(buffer.mark(), 2);
+}
+
+#[cfg(feature = "pod")]
+#[test]
+fn test_buffer_view_as() {
+    use crate::buffer::error::BufferError;
+
+    let mut buffer = CloneByteBuffer::new2(20, 20);
+    // start on a 4-byte boundary whatever the allocation
+    let pad = buffer.as_ptr().align_offset(4) as i32;
+    buffer.position_(pad);
+    for x in [1u32, 2, 0xdead_beef].iter() {
+        buffer.put_iter(x.to_ne_bytes().iter().copied());
+    }
+    buffer.flip();
+    buffer.position_(pad);
+    assert_eq!(&*buffer.view_as::<u32>().unwrap(), &[1, 2, 0xdead_beef]);
+    assert_eq!(buffer.view_as::<[u8; 4]>().unwrap().len(), 3);
+
+    buffer.limit_(pad + 11);
+    assert_eq!(buffer.view_as::<u32>().err(), Some(BufferError::SizeMismatch));
+    buffer.limit_(pad + 9);
+    buffer.position_(pad + 1);
+    assert_eq!(buffer.view_as::<u32>().err(), Some(BufferError::Misaligned));
+}
+
+#[test]
+fn test_buffer_ffi_with_header() {
+    use crate::buffer::clone_bytebuffer::FFI_HEADER_LEN;
+    use crate::buffer::error::BufferError;
+
+    let mut buffer = CloneByteBuffer::new3(&[9, 1, 2, 3], 1, 3);
+    buffer.buffer.read_only = true;
+    let bb = buffer.to_ffi_with_header();
+    assert_eq!(bb.as_slice().len(), FFI_HEADER_LEN + 3);
+    assert_eq!(&bb.as_slice()[..FFI_HEADER_LEN], &[0, 0, 0, 3, 0, 1, 0, 0]);
+
+    let mut back = CloneByteBuffer::from_ffi_with_header(bb).unwrap();
+    assert_eq!(back.position(), 0);
+    assert_eq!(back.limit(), 3);
+    assert_eq!(back.cap(), 3);
+    assert!(back.buffer.read_only);
+    assert_eq!(back.get(), 1);
+    assert_eq!(back.get(), 2);
+    assert_eq!(back.get(), 3);
+
+    let bb = crate::bytebuffer::ByteBuffer::from_vec(vec![0, 0, 0, 5, 0, 0, 0, 0, 1]);
+    assert_eq!(CloneByteBuffer::from_ffi_with_header(bb).err(), Some(BufferError::SizeMismatch));
+}
+
+#[test]
+fn test_buffer_drain_chunk() {
+    let data: Vec<u8> = (0..10).collect();
+    let mut buffer = CloneByteBuffer::new3(&data, 0, 10);
+    let mut chunks = Vec::new();
+    while buffer.has_remaining() {
+        chunks.push(buffer.drain_chunk(3));
+    }
+    assert_eq!(chunks, vec![vec![0, 1, 2], vec![3, 4, 5], vec![6, 7, 8], vec![9]]);
+    assert_eq!(buffer.drain_chunk(3), vec![]);
+}
+
+#[test]
+fn test_buffer_hash_into() {
+    use std::collections::hash_map::DefaultHasher;
+    use std::hash::Hasher;
+
+    let mut buffer = CloneByteBuffer::new3(&[1, 2, 3, 4, 5], 0, 5);
+    buffer.get();
+    let mut h1 = DefaultHasher::new();
+    buffer.hash_into(&mut h1);
+    let mut h2 = DefaultHasher::new();
+    h2.write(&[2, 3, 4, 5]);
+    assert_eq!(h1.finish(), h2.finish());
+    assert_eq!(buffer.position(), 1);
+}
+
+#[test]
+fn test_buffer_new_aligned() {
+    for &align in [1usize, 16, 32, 64].iter() {
+        let mut buffer = CloneByteBuffer::new_aligned(100, align);
+        assert_eq!(buffer.align(), align);
+        assert_eq!(buffer.as_ptr() as usize % align, 0);
+        assert_eq!(buffer.cap(), 100);
+        assert_eq!(buffer.remaining(), 100);
+        buffer.put(1);
+        buffer.flip();
+        assert_eq!(buffer.get(), 1);
+    }
+}
+
+#[test]
+fn test_buffer_new_aligned_realloc() {
+    let buffer = CloneByteBuffer::new_aligned(100, 32);
+    // a duplicate shares the aligned allocation, a copy doesn't
+    assert_eq!(buffer.duplicate().align(), 32);
+    assert_eq!(buffer.copy().align(), 1);
+    assert_eq!(buffer.clone().align(), 1);
+
+    let mut grown = buffer.duplicate();
+    grown.append_buffer(&CloneByteBuffer::new3(&[1; 64], 0, 64));
+    assert_eq!(grown.align(), 1);
+    assert_eq!(buffer.align(), 32);
+}
+
+#[test]
+#[should_panic(expected = "illegal argument!")]
+fn test_buffer_new_aligned_not_power_of_two() {
+    CloneByteBuffer::new_aligned(100, 24);
+}
+
+#[test]
+fn test_buffer_set_state() {
+    use crate::buffer::error::BufferError;
+
+    let mut buffer = CloneByteBuffer::new2(10, 10);
+    buffer.position_(8).mark_();
+    let saved = (buffer.position(), buffer.limit(), buffer.mark());
+
+    buffer.flip_to(2);
+    buffer.set_state(saved.0, saved.1, saved.2).unwrap().get();
+    assert_eq!(buffer.position(), 9);
+    assert_eq!(buffer.limit(), 10);
+    assert_eq!(buffer.mark(), 8);
+
+    assert_eq!(buffer.set_state(5, 4, -1).err(), Some(BufferError::InvalidState));
+    assert_eq!(buffer.set_state(5, 6, 6).err(), Some(BufferError::InvalidState));
+    assert_eq!(buffer.set_state(5, 11, -1).err(), Some(BufferError::InvalidState));
+    assert_eq!(buffer.position(), 9);
+}
+
+#[test]
+fn test_buffer_drain_to() {
+    let mut buffer = CloneByteBuffer::new3(&[1, 2, 3, 4], 1, 3);
+    let mut out = Vec::new();
+    buffer.drain_to(|bytes| out.extend_from_slice(bytes));
+    assert_eq!(out, vec![2, 3, 4]);
+    assert!(!buffer.has_remaining());
+}
+
+#[test]
+fn test_buffer_interleave() {
+    let left = CloneByteBuffer::new3(&[1, 3, 5], 0, 3);
+    let right = CloneByteBuffer::new3(&[0, 2, 4, 6], 1, 3);
+    let mut stereo = left.interleave(&right);
+    assert_eq!(stereo.position(), 0);
+    assert_eq!(stereo.limit(), 6);
+    assert_eq!(stereo.drain_chunk(6), vec![1, 2, 3, 4, 5, 6]);
+}
+
+#[test]
+#[should_panic(expected = "remaining lengths differ")]
+fn test_buffer_interleave_unequal() {
+    let left = CloneByteBuffer::new3(&[1, 3, 5], 0, 3);
+    let right = CloneByteBuffer::new3(&[2, 4], 0, 2);
+    left.interleave(&right);
+}
+
+#[test]
+fn test_buffer_deinterleave() {
+    let stereo = CloneByteBuffer::new3(&[1, 2, 3, 4, 5, 6], 0, 6);
+    let mut channels = stereo.deinterleave(2);
+    assert_eq!(channels.len(), 2);
+    assert_eq!(channels[0].drain_chunk(3), vec![1, 3, 5]);
+    assert_eq!(channels[1].drain_chunk(3), vec![2, 4, 6]);
+    assert_eq!(stereo.deinterleave(1)[0].remaining(), 6);
+}
+
+#[test]
+#[should_panic(expected = "remaining is not a multiple of 4")]
+fn test_buffer_deinterleave_not_divisible() {
+    let stereo = CloneByteBuffer::new3(&[1, 2, 3, 4, 5, 6], 0, 6);
+    stereo.deinterleave(4);
+}
+
+#[test]
+fn test_buffer_pad_start() {
+    let mut buffer = CloneByteBuffer::new3(&[1, 2, 3], 0, 3);
+    buffer.pad_start(5, 0x00);
+    assert_eq!(buffer.position(), 0);
+    assert_eq!(buffer.limit(), 5);
+    assert_eq!(buffer.cap(), 5);
+    assert_eq!(buffer.drain_chunk(5), vec![0, 0, 1, 2, 3]);
+
+    // room left in the buffer: no growth
+    let mut buffer = CloneByteBuffer::new3(&[9, 1, 2, 0, 0, 0], 1, 2);
+    buffer.pad_start(4, 0x20);
+    assert_eq!(buffer.cap(), 6);
+    assert_eq!(buffer.drain_chunk(4), vec![0x20, 0x20, 1, 2]);
+
+    // already long enough
+    let mut buffer = CloneByteBuffer::new3(&[1, 2, 3], 0, 3);
+    buffer.pad_start(2, 0x00);
+    assert_eq!(buffer.drain_chunk(5), vec![1, 2, 3]);
+}
+
+#[test]
+fn test_buffer_trim() {
+    let mut buffer = CloneByteBuffer::new3(b"\0\0\x07ab  ", 0, 7);
+    buffer.trim_start(0).trim_end(b' ');
+    assert_eq!(buffer.position(), 2);
+    assert_eq!(buffer.limit(), 5);
+    assert_eq!(buffer.drain_chunk(10), b"\x07ab".to_vec());
+
+    let mut buffer = CloneByteBuffer::new3(b"    ", 0, 4);
+    buffer.trim_end(b' ');
+    assert_eq!(buffer.limit(), 0);
+    assert!(!buffer.has_remaining());
+}
+
+#[test]
+fn test_buffer_round_trip() {
+    use crate::tests::testutil::assert_round_trip;
+
+    for &x in &[0, -1, i32::MIN, i32::MAX, 0x0102_0304] {
+        assert_round_trip(x, |b, x| b.try_put_i32(x).unwrap(), |b| b.try_get_i32().unwrap());
+    }
+    for &x in &[0, -1, i64::MIN, i64::MAX] {
+        assert_round_trip(x, |b, x| b.try_put_i64(x).unwrap(), |b| b.try_get_i64().unwrap());
+    }
+    for &x in &[0.0, -0.25, f64::MAX, f64::MIN_POSITIVE, f64::INFINITY] {
+        assert_round_trip(x, |b, x| b.try_put_f64(x).unwrap(), |b| b.try_get_f64().unwrap());
+    }
+}
+
+#[test]
+fn test_buffer_read_into_slice() {
+    let mut buffer = CloneByteBuffer::new3(&[1, 2, 3, 4, 5, 6], 0, 6);
+    let mut dst = [0u8; 4];
+    buffer.read_into_slice(&mut dst);
+    assert_eq!(dst, [1, 2, 3, 4]);
+    assert_eq!(buffer.position(), 4);
+}
+
+#[test]
+#[should_panic(expected = "buffer under flow")]
+fn test_buffer_read_into_slice_under_flow() {
+    let mut buffer = CloneByteBuffer::new3(&[1, 2, 3], 0, 3);
+    buffer.read_into_slice(&mut [0u8; 4]);
+}
+
+#[test]
+fn test_buffer_remaining_usize() {
+    let buffer = CloneByteBuffer::new3(&[1, 2, 3, 4], 1, 2);
+    assert_eq!(buffer.buffer.buffer.remaining_usize(), 2);
+}
+
+#[test]
+#[should_panic(expected = "negative remaining")]
+fn test_buffer_put_buffer_inverted() {
+    let mut src = CloneByteBuffer::new3(&[1, 2, 3, 4], 0, 4);
+    // position past limit, as a buggy caller poking the fields could leave it
+    src.buffer.buffer.position = 3;
+    src.buffer.buffer.limit = 1;
+    let mut dst = CloneByteBuffer::new2(4, 4);
+    dst.put_buffer(&mut src);
+}
+
+#[test]
+fn test_buffer_slice_duplicate_state() {
+    let mut buffer = CloneByteBuffer::new2(8, 6);
+    buffer.position_(2).mark_().position_(3);
+
+    let slice = buffer.slice();
+    assert_eq!(slice.position(), 0);
+    assert_eq!(slice.limit(), 3);
+    assert_eq!(slice.cap(), 3);
+    assert_eq!(slice.mark(), -1);
+    assert_eq!(slice.offset, 3);
+
+    let dup = buffer.duplicate();
+    assert_eq!(dup.position(), 3);
+    assert_eq!(dup.limit(), 6);
+    assert_eq!(dup.cap(), 8);
+    assert_eq!(dup.mark(), 2);
+    assert_eq!(dup.offset, buffer.offset);
+    // the original is still usable
+    assert_eq!(buffer.reset().position(), 2);
+}
+
+#[test]
+fn test_buffer_get_put_u128() {
+    use std::net::Ipv6Addr;
+
+    let addr: Ipv6Addr = "2001:db8::ff00:42:8329".parse().unwrap();
+    let mut buffer = CloneByteBuffer::new2(32, 32);
+    buffer.put_u128(u128::MAX).put_u128(u128::from(addr));
+    assert_eq!(buffer.position(), 32);
+    buffer.flip();
+    assert_eq!(buffer.get_u128(), u128::MAX);
+    assert_eq!(Ipv6Addr::from(buffer.get_u128()), addr);
+    assert!(!buffer.has_remaining());
+}
+
+#[test]
+#[should_panic(expected = "buffer under flow!")]
+fn test_buffer_get_u128_under_flow() {
+    let mut buffer = CloneByteBuffer::new2(15, 15);
+    buffer.get_u128();
+}
+
+#[test]
+fn test_buffer_share_copy() {
+    let mut buffer = CloneByteBuffer::new3(&[1, 2, 3, 4], 0, 4);
+    buffer.position_(1).mark_().position_(2);
+
+    let mut shared = buffer.share();
+    let mut copied = buffer.copy();
+    for b in [&shared, &copied] {
+        assert_eq!((b.mark(), b.position(), b.limit(), b.cap()), (1, 2, 4, 4));
+    }
+
+    copied.put(9);
+    assert_eq!(copied.hb.borrow()[2], 9);
+    assert_eq!(buffer.hb.borrow()[2], 3);
+
+    // writes through either shared handle show up in the other
+    shared.put(7);
+    assert_eq!(buffer.get_i(2), 7);
+    buffer.put_i(8, 3);
+    assert_eq!(shared.get_i(3), 8);
+    assert_eq!(copied.get_i(3), 4);
+}
+
+#[test]
+fn test_buffer_read_vectored() {
+    use std::io::IoSliceMut;
+
+    let mut buffer = CloneByteBuffer::new2(8, 8);
+    buffer.put_buf(&mut vec![1, 2, 3, 4, 5], 0, 5);
+    buffer.flip();
+
+    let (mut a, mut b) = ([0u8; 2], [0u8; 4]);
+    let n = buffer.read_vectored(&mut [IoSliceMut::new(&mut a), IoSliceMut::new(&mut b)]);
+    assert_eq!(n, 5);
+    assert_eq!(a, [1, 2]);
+    assert_eq!(b, [3, 4, 5, 0]);
+    assert!(!buffer.has_remaining());
+}
+
+#[test]
+fn test_buffer_from_static() {
+    use crate::buffer::borrowed_bytebuffer::BorrowedByteBuffer;
+    use crate::buffer::error::BufferError;
+
+    const TABLE: [u8; 15] = [0xff, 0xfe, 0, 0, 1, 0, 0x3f, 0xf8, 0, 0, 0, 0, 0, 0, 7];
+    let mut buffer = BorrowedByteBuffer::from_static(&TABLE);
+    assert!(buffer.buffer.read_only);
+    assert_eq!(buffer.get_i16(), -2);
+    assert_eq!(buffer.get_i32(), 0x100);
+    assert_eq!(buffer.get_f64(), 1.5);
+    assert_eq!(buffer.as_slice(), &[7]);
+    assert_eq!(buffer.get(), 7);
+    assert!(!buffer.has_remaining());
+    assert_eq!(buffer.try_get(), Err(BufferError::Underflow));
+    assert!(std::ptr::eq(buffer.rewind().as_slice(), &TABLE[..]));
+}
+
+#[test]
+#[should_panic(expected = "read only buffer!")]
+fn test_buffer_from_static_put() {
+    use crate::buffer::borrowed_bytebuffer::BorrowedByteBuffer;
+
+    static DATA: [u8; 2] = [0; 2];
+    let mut buffer = BorrowedByteBuffer::from_static(&DATA);
+    buffer.try_put(1).unwrap();
+}
+
+#[test]
+fn test_buffer_get_put_with_order() {
+    use crate::buffer::byte_order::ByteOrder::{BigEndian, LittleEndian};
+
+    let mut buffer = CloneByteBuffer::new3(&[0, 0, 1, 2, 2, 1, 0, 0], 0, 8);
+    assert_eq!(buffer.get_i32_with(BigEndian), 0x0102);
+    assert_eq!(buffer.get_i32_with(LittleEndian), 0x0102);
+
+    let mut buffer = CloneByteBuffer::new2(26, 26);
+    buffer.put_i16_with(-2, LittleEndian)
+        .put_i64_with(-3, BigEndian)
+        .put_f32_with(1.5, LittleEndian)
+        .put_f64_with(-0.25, BigEndian)
+        .put_i32_with(7, LittleEndian);
+    buffer.flip();
+    assert_eq!(&buffer.hb.borrow()[..2], &[0xfe, 0xff]);
+    assert_eq!(buffer.get_i16_with(LittleEndian), -2);
+    assert_eq!(buffer.get_i64_with(BigEndian), -3);
+    assert_eq!(buffer.get_f32_with(LittleEndian), 1.5);
+    assert_eq!(buffer.get_f64_with(BigEndian), -0.25);
+    assert_eq!(buffer.get_i32_with(LittleEndian), 7);
+}
+
+#[test]
+fn test_buffer_into_vec() {
+    let mut buffer = CloneByteBuffer::new2(4, 4);
+    buffer.put_buf(&mut vec![1, 2, 3, 4], 0, 4);
+    let ptr = buffer.hb.borrow().as_ptr();
+    let v = buffer.into_vec();
+    assert_eq!(v, vec![1, 2, 3, 4]);
+    // handed over, not copied
+    assert_eq!(v.as_ptr(), ptr);
+
+    let mut buffer = CloneByteBuffer::new2(4, 4);
+    buffer.put_buf(&mut vec![1, 2, 3], 0, 3);
+    buffer.flip();
+    assert_eq!(buffer.into_vec(), vec![1, 2, 3]);
+}
+
+#[test]
+fn test_buffer_into_vec_slice() {
+    let mut buffer = CloneByteBuffer::new3(&[1, 2, 3, 4, 5, 6], 0, 6);
+    buffer.position_(2);
+    let mut slice = buffer.slice();
+    slice.limit_(3);
+    assert_eq!(slice.into_vec(), vec![3, 4, 5]);
+}
+
+#[test]
+fn test_buffer_put_buffer_overlap() {
+    let data: Vec<u8> = (0..10).collect();
+    let mut expected = data.clone();
+    expected.copy_within(2..8, 4);
+
+    let mut parent = CloneByteBuffer::new3(&data, 0, 10);
+    parent.position_(2);
+    let mut src = parent.slice();
+    src.limit_(6);
+    parent.position_(4);
+    parent.put_buffer(&mut src);
+    assert_eq!(*parent.hb.borrow(), expected);
+    assert_eq!(parent.position(), 10);
+    assert!(!src.has_remaining());
+}
+
+#[test]
+fn test_buffer_stats() {
+    use crate::buffer::clone_bytebuffer::BufferStats;
+
+    let mut buffer = CloneByteBuffer::new2(16, 16);
+    buffer.position_(4);
+    let mut slice = buffer.slice();
+    slice.limit_(10).position_(3);
+    assert_eq!(slice.stats(), BufferStats {
+        capacity: 12,
+        used: 10,
+        position: 3,
+        remaining: 7,
+        backing_len: 16,
+        offset: 4,
+        read_only: false,
+    });
+}
+
+#[test]
+fn test_buffer_get_i32() {
+    let mut buffer = CloneByteBuffer::new2(8, 8);
+    for x in 1..=5 {
+        buffer.put(x);
+    }
+    buffer.flip();
+    assert_eq!(buffer.get_i32(), 0x0102_0304);
+    assert_eq!(buffer.position(), 4);
+    assert_eq!(buffer.get(), 5);
+}
+
+#[test]
+fn test_buffer_get_i16_i64() {
+    let mut buffer = CloneByteBuffer::new3(&[0xff, 0xfe, 0x80, 0, 0, 0, 0, 0, 0, 1], 0, 10);
+    assert_eq!(buffer.get_i16(), -2);
+    assert_eq!(buffer.get_i64(), i64::MIN + 1);
+}
+
+#[test]
+#[should_panic(expected = "buffer under flow")]
+fn test_buffer_get_i64_under_flow() {
+    let mut buffer = CloneByteBuffer::new2(8, 7);
+    buffer.get_i64();
+}
+
+#[test]
+fn test_buffer_put_uint() {
+    use crate::buffer::error::BufferError;
+
+    let mut buffer = CloneByteBuffer::new2(8, 8);
+    buffer.put_uint_checked(0xff_ffff, 3).unwrap();
+    assert_eq!(buffer.put_uint_checked(0x100_0000, 3), Err(BufferError::ValueTooWide));
+    assert_eq!(buffer.position(), 3);
+    buffer.put_uint_truncating(0x1234_5678, 2);
+    buffer.put_uint_checked(u64::MAX, 8).unwrap_err();
+    assert_eq!(buffer.put_uint_checked(1, 4), Err(BufferError::Overflow));
+    buffer.flip();
+    assert_eq!(buffer.slice_to_vec(0, 5), vec![0xff, 0xff, 0xff, 0x56, 0x78]);
+}
+
+#[test]
+#[should_panic(expected = "illegal argument!")]
+fn test_buffer_put_uint_width() {
+    CloneByteBuffer::new2(16, 16).put_uint_truncating(1, 9);
+}
+
+#[test]
+fn test_buffer_put_i64() {
+    let mut buffer = CloneByteBuffer::new2(8, 8);
+    buffer.put_i64(0x0102030405060708);
+    buffer.flip();
+    assert_eq!(*buffer.hb.borrow(), vec![1, 2, 3, 4, 5, 6, 7, 8]);
+    assert_eq!(buffer.get_i64(), 0x0102030405060708);
+}
+
+#[test]
+fn test_buffer_put_i16_i32() {
+    let mut buffer = CloneByteBuffer::new2(6, 6);
+    buffer.put_i16(-2).put_i32(0x0102_0304);
+    buffer.flip();
+    assert_eq!(*buffer.hb.borrow(), vec![0xff, 0xfe, 1, 2, 3, 4]);
+    assert_eq!(buffer.get_i16(), -2);
+    assert_eq!(buffer.get_i32(), 0x0102_0304);
+}
+
+#[test]
+#[should_panic(expected = "buffer over flow!")]
+fn test_buffer_put_i32_over_flow() {
+    let mut buffer = CloneByteBuffer::new2(8, 3);
+    buffer.put_i32(1);
+}
+
+#[test]
+fn test_buffer_varint() {
+    let mut buffer = CloneByteBuffer::new2(32, 32);
+    buffer.put_varint(0).put_varint(300).put_varint(u64::MAX);
+    assert_eq!(buffer.position(), 1 + 2 + 10);
+    buffer.flip();
+    assert_eq!(buffer.slice_to_vec(1, 3), vec![0xac, 0x02]);
+    assert_eq!(buffer.get_varint(), 0);
+    assert_eq!(buffer.get_varint(), 300);
+    assert_eq!(buffer.get_varint(), u64::MAX);
+}
+
+#[test]
+fn test_buffer_bytes_prefixed() {
+    let blob: Vec<u8> = (0..200u8).collect();
+    let mut buffer = CloneByteBuffer::new2(256, 256);
+    buffer.put_bytes_prefixed(&blob).put_bytes_prefixed(&[]);
+    buffer.flip();
+    assert_eq!(buffer.remaining(), 2 + 200 + 1);
+    assert_eq!(buffer.get_bytes_prefixed(), blob);
+    assert_eq!(buffer.get_bytes_prefixed(), Vec::<u8>::new());
+    assert!(!buffer.has_remaining());
+}
+
+#[test]
+#[should_panic(expected = "buffer under flow")]
+fn test_buffer_bytes_prefixed_truncated() {
+    let mut buffer = CloneByteBuffer::new3(&[5, 1, 2], 0, 3);
+    buffer.get_bytes_prefixed();
+}
+
+#[test]
+fn test_buffer_order() {
+    use crate::buffer::byte_order::ByteOrder;
+
+    let mut buffer = CloneByteBuffer::new2(32, 32);
+    assert_eq!(buffer.order(), ByteOrder::BigEndian);
+    buffer.order_(ByteOrder::LittleEndian);
+    buffer.put_i16(0x0102).put_i32(0x0304_0506).put_i64(-2);
+    buffer.try_put_f32(1.5).unwrap();
+    buffer.put_uint_truncating(0x0a_0b0c, 3);
+    buffer.flip();
+    assert_eq!(buffer.slice_to_vec(0, 6), vec![2, 1, 6, 5, 4, 3]);
+    assert_eq!(buffer.slice_to_vec(18, 21), vec![0x0c, 0x0b, 0x0a]);
+
+    let slice = buffer.slice();
+    assert_eq!(slice.order(), ByteOrder::LittleEndian);
+
+    assert_eq!(buffer.get_i16(), 0x0102);
+    assert_eq!(buffer.get_i32(), 0x0304_0506);
+    assert_eq!(buffer.get_i64(), -2);
+    assert_eq!(buffer.try_get_f32(), Ok(1.5));
+
+    buffer.rewind().order_(ByteOrder::BigEndian);
+    assert_eq!(buffer.get_i16(), 0x0201);
+}
+
+#[test]
+fn test_buffer_order_ffi_header() {
+    use crate::buffer::byte_order::ByteOrder;
+
+    let mut buffer = CloneByteBuffer::new3(&[1, 2], 0, 2);
+    buffer.order_(ByteOrder::LittleEndian);
+    let back = CloneByteBuffer::from_ffi_with_header(buffer.to_ffi_with_header()).unwrap();
+    assert_eq!(back.order(), ByteOrder::LittleEndian);
+}
+
+#[test]
+fn test_buffer_reader_lines() {
+    use std::io::{BufRead, Read};
+
+    let mut buffer = CloneByteBuffer::new3(b"xxfirst\nsecond\r\nthird", 0, 21);
+    buffer.position_(2);
+    let lines: Vec<String> = buffer.reader().lines().map(Result::unwrap).collect();
+    assert_eq!(lines, vec!["first", "second", "third"]);
+    assert!(!buffer.has_remaining());
+
+    buffer.position_(8);
+    let mut reader = buffer.reader();
+    let mut word = Vec::new();
+    reader.read_until(b'\r', &mut word).unwrap();
+    assert_eq!(word, b"second\r");
+    let mut rest = String::new();
+    reader.read_to_string(&mut rest).unwrap();
+    assert_eq!(rest, "\nthird");
+}
+
+#[test]
+fn test_buffer_get_circular() {
+    let mut buffer = CloneByteBuffer::new3(&[1, 2, 3, 9], 0, 3);
+    let read: Vec<u8> = (0..7).map(|_| buffer.get_circular()).collect();
+    assert_eq!(read, vec![1, 2, 3, 1, 2, 3, 1]);
+    assert_eq!(buffer.position(), 1);
+
+    buffer.circular_(true);
+    assert!(buffer.is_circular());
+    let read: Vec<u8> = (0..4).map(|_| buffer.get()).collect();
+    assert_eq!(read, vec![2, 3, 1, 2]);
+}
+
+#[test]
+#[should_panic(expected = "buffer under flow!")]
+fn test_buffer_get_circular_empty() {
+    let mut buffer = CloneByteBuffer::new2(4, 0);
+    buffer.get_circular();
+}
+
+#[test]
+fn test_buffer_diff() {
+    let a = CloneByteBuffer::new3(&[9, 1, 2, 3, 4, 5], 1, 5);
+    let b = CloneByteBuffer::new3(&[1, 7, 3, 4, 6], 0, 5);
+    assert_eq!(a.diff(&b), vec![(1, Some(2), Some(7)), (4, Some(5), Some(6))]);
+    assert!(a.diff(&a.duplicate()).is_empty());
+
+    let short = CloneByteBuffer::new3(&[1, 2, 3], 0, 3);
+    assert_eq!(a.diff(&short), vec![(3, Some(4), None), (4, Some(5), None)]);
+    assert_eq!(short.diff(&a), vec![(3, None, Some(4)), (4, None, Some(5))]);
+    assert_eq!(a.position(), 1);
+}
+
+#[test]
+fn test_buffer_diff_trailing_zeros() {
+    let a = CloneByteBuffer::new3(&[1, 2, 0, 0], 0, 4);
+    let b = CloneByteBuffer::new3(&[1, 2], 0, 2);
+    assert_eq!(a.diff(&b), vec![(2, Some(0), None), (3, Some(0), None)]);
+    assert_eq!(b.diff(&a), vec![(2, None, Some(0)), (3, None, Some(0))]);
+}
+
+#[test]
+fn test_buffer_apply_patch() {
+    let mut buffer = CloneByteBuffer::new3(&[0, 1, 2, 3, 4, 5], 0, 6);
+    buffer.position_(2);
+    let mut slice = buffer.slice();
+    slice.position_(1);
+    slice.apply_patch(&[(0, 0xa), (3, 0xd), (1, 0xb)]);
+    assert_eq!(slice.position(), 1);
+    assert_eq!(*slice.hb.borrow(), vec![0, 1, 0xa, 0xb, 4, 0xd]);
+}
+
+#[test]
+fn test_buffer_apply_patch_all_or_nothing() {
+    use std::panic::{catch_unwind, AssertUnwindSafe};
+
+    let mut buffer = CloneByteBuffer::new3(&[0, 1, 2, 3], 0, 4);
+    buffer.limit_(3);
+    let result = catch_unwind(AssertUnwindSafe(|| {
+        buffer.apply_patch(&[(0, 9), (3, 9)]);
+    }));
+    assert!(result.is_err());
+    assert_eq!(*buffer.hb.borrow(), vec![0, 1, 2, 3]);
+}
+
+#[test]
+fn test_buffer_read_exact() {
+    use crate::buffer::error::BufferError;
+
+    const MAGIC: [u8; 4] = *b"\x89PNG";
+    let mut buffer = CloneByteBuffer::new3(b"\x89PNG\r\n", 0, 6);
+    assert_eq!(buffer.read_exact::<4>(), Ok(MAGIC));
+    assert_eq!(buffer.position(), 4);
+
+    let mut truncated = CloneByteBuffer::new3(b"\x89PN", 0, 3);
+    assert_eq!(truncated.read_exact::<4>(), Err(BufferError::Underflow));
+    assert_eq!(truncated.position(), 0);
+}
+
+#[test]
+fn test_buffer_as_read_only() {
+    let mut bb = ByteBuffer::new_(-1, 2, 6, 8);
+    bb.mark_();
+    assert!(!bb.is_read_only());
+    let ro = bb.as_read_only();
+    assert!(ro.is_read_only());
+    assert_eq!((ro.mark(), ro.position(), ro.limit(), ro.cap()), (2, 2, 6, 8));
+    assert!(!bb.is_read_only());
+}
+
+#[test]
+#[should_panic(expected = "read only buffer")]
+fn test_buffer_as_read_only_put() {
+    let mut ro = ByteBuffer::new_(-1, 0, 8, 8).as_read_only();
+    ro.put(1);
+}
+
+#[test]
+fn test_buffer_truncate_chain() {
+    let mut buffer = Buffer::new_(-1, 3, 6, 8);
+    let position = buffer.clear().truncate().flip().position();
+    assert_eq!(position, 0);
+    assert_eq!((buffer.limit(), buffer.cap()), (0, 0));
+
+    let bb = ByteBuffer::new_(-1, 3, 6, 8).into_truncated();
+    assert_eq!((bb.position(), bb.limit(), bb.cap()), (0, 0, 0));
+}
+
+
+#[test]
+fn test_buffer_share_visible() {
+    let mut buffer = CloneByteBuffer::new3(&[1, 2, 3, 4], 0, 4);
+    let mut shared = buffer.share();
+    let mut dup = buffer.duplicate();
+    shared.put(7);
+    dup.position_(1).put(8);
+    assert_eq!(*buffer.hb.borrow(), vec![7, 8, 3, 4]);
+
+    let mut copied = buffer.clone();
+    copied.put(9);
+    assert_eq!(buffer.get(), 7);
+}
+
+#[test]
+fn test_buffer_grow_shared() {
+    let mut buffer = CloneByteBuffer::new3(&[1, 2, 3, 4], 0, 4);
+    buffer.position_(1);
+    let mut slice = buffer.slice();
+    slice.limit_(2);
+    // growing a slice must not shift the parent's bytes
+    slice.pad_start(4, 0);
+    assert_eq!(*buffer.hb.borrow(), vec![1, 2, 3, 4]);
+    assert_eq!(slice.drain_chunk(4), vec![0, 0, 2, 3]);
+}
+#[cfg(feature = "pod")]
+#[test]
+fn test_buffer_read_structs() {
+    use crate::buffer::pod::Pod;
+
+    #[repr(C)]
+    #[derive(Debug, Clone, Copy, PartialEq)]
+    struct Glyph {
+        code: u16,
+        width: u16,
+        offset: u32,
+    }
+    unsafe impl Pod for Glyph {}
+
+    let glyphs = [
+        Glyph { code: 65, width: 8, offset: 0 },
+        Glyph { code: 66, width: 9, offset: 64 },
+        Glyph { code: 67, width: 7, offset: 136 },
+    ];
+    let mut buffer = CloneByteBuffer::new2(40, 40);
+    // an odd start, so the source is misaligned for `Glyph`
+    buffer.position_(1);
+    for g in glyphs.iter() {
+        buffer.put_iter(g.code.to_ne_bytes().iter().copied());
+        buffer.put_iter(g.width.to_ne_bytes().iter().copied());
+        buffer.put_iter(g.offset.to_ne_bytes().iter().copied());
+    }
+    buffer.flip();
+    buffer.position_(1);
+    assert_eq!(buffer.read_structs::<Glyph>(3), glyphs.to_vec());
+    assert_eq!(buffer.position(), 25);
+    assert!(buffer.read_structs::<Glyph>(0).is_empty());
+}
+
+#[cfg(feature = "pod")]
+#[test]
+#[should_panic(expected = "buffer under flow!")]
+fn test_buffer_read_structs_underflow() {
+    let mut buffer = CloneByteBuffer::new2(7, 7);
+    buffer.read_structs::<u32>(2);
+}
+
+#[test]
+fn test_buffer_get_i32_buf() {
+    let mut buffer = CloneByteBuffer::new2(16, 16);
+    for x in [1, -2, 0x0102_0304, i32::MIN].iter() {
+        buffer.put_i32(*x);
+    }
+    buffer.flip();
+    buffer.get_i32();
+    let mut dst = vec![0; 5];
+    buffer.get_i32_buf(&mut dst, 1, 3);
+    assert_eq!(dst, vec![0, -2, 0x0102_0304, i32::MIN, 0]);
+    assert_eq!(buffer.position(), 16);
+}
+
+#[test]
+fn test_buffer_get_i32_buf_little_endian() {
+    use crate::buffer::byte_order::ByteOrder;
+
+    let mut buffer = CloneByteBuffer::new3(&[1, 0, 0, 0, 0x04, 0x03, 0x02, 0x01], 0, 8);
+    buffer.order_(ByteOrder::LittleEndian);
+    let mut dst = vec![0; 2];
+    buffer.get_i32_buf(&mut dst, 0, 2);
+    assert_eq!(dst, vec![1, 0x0102_0304]);
+}
+
+#[test]
+#[should_panic(expected = "buffer under flow")]
+fn test_buffer_get_i32_buf_under_flow() {
+    let mut buffer = CloneByteBuffer::new2(7, 7);
+    let mut dst = vec![0; 2];
+    buffer.get_i32_buf(&mut dst, 0, 2);
+}
+
+#[test]
+fn test_buffer_compact() {
+    let mut buffer = CloneByteBuffer::new3(&[1, 2, 3, 4, 5], 0, 5);
+    buffer.get();
+    buffer.get();
+    buffer.mark_();
+    buffer.compact();
+    assert_eq!((buffer.position(), buffer.limit(), buffer.mark()), (3, 5, -1));
+    assert_eq!(*buffer.hb.borrow(), vec![3, 4, 5, 4, 5]);
+}
+
+#[test]
+fn test_buffer_compact_slice() {
+    let mut parent = CloneByteBuffer::new3(&[1, 2, 3, 4, 5, 6, 7, 8], 2, 4);
+    let mut slice = parent.slice();
+    assert_eq!(slice.offset, 2);
+    slice.get();
+    slice.compact();
+    assert_eq!((slice.position(), slice.limit()), (3, 4));
+    // only the slice's region [2, 6) moved; the parent's bytes around it are intact
+    assert_eq!(*parent.hb.borrow(), vec![1, 2, 4, 5, 6, 6, 7, 8]);
+    assert_eq!(parent.get(), 4);
+}
+
+#[test]
+fn test_buffer_get_i_put_i() {
+    let mut buffer = CloneByteBuffer::new3(&[1, 2, 3, 4, 5], 0, 5);
+    buffer.get();
+    buffer.put_i(9, 3);
+    assert_eq!(buffer.get_i(3), 9);
+    assert_eq!(buffer.get_i(0), 1);
+    assert_eq!(buffer.position(), 1);
+
+    // indices are relative to the slice, and writes through shared storage are visible
+    let mut slice = buffer.slice();
+    assert_eq!(slice.get_i(0), 2);
+    assert_eq!(slice.get_i(2), 9);
+    slice.put_i(7, 0);
+    assert_eq!(buffer.get_i(1), 7);
+}
+
+#[test]
+#[should_panic(expected = "index out of bound")]
+fn test_buffer_get_i_past_limit() {
+    let mut buffer = CloneByteBuffer::new3(&[1, 2, 3, 4, 5], 0, 5);
+    buffer.limit_(2);
+    buffer.get_i(2);
+}
+
+#[test]
+fn test_buffer_get_utf8_chunk() {
+    let text = "ab\u{1F600}c";
+    let mut buffer = CloneByteBuffer::new3(text.as_bytes(), 0, text.len() as i32);
+    // the 4-byte emoji starts at byte 2 and straddles the first boundary
+    assert_eq!(buffer.get_utf8_chunk(4), ("ab".to_string(), 2));
+    assert_eq!(buffer.position(), 2);
+    assert_eq!(buffer.get_utf8_chunk(4), ("\u{1F600}".to_string(), 4));
+    assert_eq!(buffer.get_utf8_chunk(4), ("c".to_string(), 1));
+    assert_eq!(buffer.get_utf8_chunk(4), (String::new(), 0));
+
+    let mut buffer = CloneByteBuffer::new3(&[b'a', 0xff, b'b'], 0, 3);
+    assert_eq!(buffer.get_utf8_chunk(8), ("a\u{FFFD}b".to_string(), 3));
+}
+
+#[test]
+fn test_buffer_wrap() {
+    let mut buffer = CloneByteBuffer::wrap(&[1, 2, 3, 4, 5, 6], 2, 3);
+    assert_eq!(buffer.position(), 2);
+    assert_eq!(buffer.remaining(), 3);
+    assert_eq!(buffer.limit(), 5);
+    assert_eq!(buffer.cap(), 6);
+    assert_eq!(buffer.mark(), -1);
+    assert_eq!(buffer.get(), 3);
+}
+
+#[test]
+#[should_panic(expected = "index out of bounds: tried to access 2 elements at offset 2 but size is 3")]
+fn test_buffer_wrap_out_of_bounds() {
+    CloneByteBuffer::wrap(&[1, 2, 3], 2, 2);
+}
+
+#[test]
+fn test_buffer_owned_copies() {
+    use crate::buffer::generic_bytebuffer::UniqueByteBuffer;
+
+    let mut buffer = UniqueByteBuffer::new3(&[1, 2, 3, 4], 0, 4);
+    let mut cloned = buffer.clone();
+    buffer.get();
+    let mut slice = buffer.slice();
+    cloned.put(9);
+    slice.put(8);
+    assert_eq!(buffer.to_vec(), vec![1, 2, 3, 4]);
+    assert_eq!(cloned.to_vec(), vec![9, 2, 3, 4]);
+    assert_eq!(slice.to_vec(), vec![8, 3, 4]);
+}
+
+#[test]
+fn test_buffer_shared_shares() {
+    use crate::buffer::generic_bytebuffer::SharedByteBuffer;
+
+    let mut buffer = SharedByteBuffer::new3(&[1, 2, 3, 4], 0, 4);
+    let mut cloned = buffer.clone();
+    buffer.get();
+    let mut slice = buffer.slice();
+    cloned.put(9);
+    slice.put(8);
+    assert_eq!(buffer.to_vec(), vec![9, 8, 3, 4]);
+    assert_eq!(cloned.to_vec(), vec![9, 8, 3, 4]);
+    assert_eq!(slice.to_vec(), vec![8, 3, 4]);
+}
+
+#[test]
+fn test_buffer_generic_typed() {
+    use crate::buffer::generic_bytebuffer::{UniqueByteBuffer, SharedByteBuffer};
+
+    let mut owned = UniqueByteBuffer::new2(14, 14);
+    owned.put_i16(-2).put_i32(0x0102_0304).put_f64(1.5);
+    owned.flip();
+    assert_eq!((owned.get_i16(), owned.get_i32(), owned.get_f64()), (-2, 0x0102_0304, 1.5));
+
+    let mut shared = SharedByteBuffer::new2(12, 12);
+    shared.put_i64(i64::MIN).put_f32(-0.25);
+    shared.flip();
+    assert_eq!((shared.get_i64(), shared.get_f32()), (i64::MIN, -0.25));
+}
+
+#[test]
+fn test_buffer_generic_truncate() {
+    use crate::buffer::borrowed_bytebuffer::BorrowedByteBuffer;
+    use crate::buffer::generic_bytebuffer::SharedByteBuffer;
+
+    // like `Buffer::truncate`: everything, capacity included, drops to zero
+    let mut shared = SharedByteBuffer::new3(&[1, 2, 3, 4], 1, 2);
+    shared.mark_().truncate();
+    assert_eq!((shared.mark(), shared.position(), shared.limit(), shared.cap()), (-1, 0, 0, 0));
+
+    let mut borrowed = BorrowedByteBuffer::new(&[1, 2, 3]);
+    borrowed.truncate();
+    assert_eq!((borrowed.mark(), borrowed.position(), borrowed.limit(), borrowed.cap()), (-1, 0, 0, 0));
+}
+
+#[test]
+fn test_buffer_generic_read_only() {
+    use std::panic::{catch_unwind, AssertUnwindSafe};
+    use crate::buffer::generic_bytebuffer::UniqueByteBuffer;
+
+    let mut buffer = UniqueByteBuffer::new2(8, 8);
+    buffer.buffer.read_only = true;
+    let writes: [&dyn Fn(&mut UniqueByteBuffer); 3] = [
+        &|b| b.put(1),
+        &|b| b.put_i(1, 0),
+        &|b| {
+            b.put_i32(1);
+        },
+    ];
+    for write in writes.iter() {
+        let err = catch_unwind(AssertUnwindSafe(|| write(&mut buffer))).unwrap_err();
+        assert_eq!(err.downcast_ref::<&str>(), Some(&"read only buffer!"));
+    }
+    assert_eq!(buffer.position(), 0);
+    assert_eq!(buffer.to_vec(), vec![0; 8]);
+}
+
+#[test]
+fn test_buffer_eq_ord() {
+    use std::collections::hash_map::DefaultHasher;
+    use std::hash::{Hash, Hasher};
+
+    let hash = |b: &CloneByteBuffer| {
+        let mut h = DefaultHasher::new();
+        b.hash(&mut h);
+        h.finish()
+    };
+    let mut a = CloneByteBuffer::new3(&[9, 9, 1, 2, 3], 2, 3);
+    let b = CloneByteBuffer::new3(&[1, 2, 3], 0, 3);
+    assert_eq!(a, b);
+    assert_eq!(hash(&a), hash(&b));
+
+    // unsigned: 0x80 sorts after 0x7f
+    let hi = CloneByteBuffer::new3(&[0x80], 0, 1);
+    let lo = CloneByteBuffer::new3(&[0x7f, 0xff], 0, 2);
+    assert!(lo < hi);
+    // a proper prefix sorts first
+    a.limit_(4);
+    assert!(a < b);
+    assert_ne!(a, b);
+}
+
+#[test]
+fn test_buffer_get_unchecked() {
+    let data: Vec<u8> = (0..32).map(|i| i * 7).collect();
+    let mut checked = CloneByteBuffer::new3(&data, 3, 20);
+    let mut unchecked = checked.clone();
+    while checked.has_remaining() {
+        assert_eq!(unsafe { unchecked.get_unchecked() }, checked.get());
+        assert_eq!(unchecked.position(), checked.position());
+    }
+}
+
+#[test]
+fn test_buffer_to_hex() {
+    let mut data = b"xxHello, hexdump!".to_vec();
+    data.extend_from_slice(&[0, 1, 0x7f, 0xff]);
+    let buffer = CloneByteBuffer::new3(&data, 2, data.len() as i32 - 2);
+    assert_eq!(
+        buffer.to_hex(),
+        "00000000  48 65 6c 6c 6f 2c 20 68  65 78 64 75 6d 70 21 00  |Hello, hexdump!.|\n\
+         00000010  01 7f ff                                          |...|\n\
+         00000013\n"
+    );
+    assert_eq!(CloneByteBuffer::new2(4, 0).to_hex(), "00000000\n");
+}
+
+#[test]
+fn test_buffer_crc32() {
+    let mut buffer = CloneByteBuffer::new3(b"xx123456789", 2, 9);
+    assert_eq!(buffer.crc32(), 0xCBF4_3926);
+    assert_eq!(buffer.position(), 2);
+    buffer.position_(11);
+    assert_eq!(buffer.crc32(), 0);
+}
+
+#[cfg(feature = "arrayvec")]
+#[test]
+fn test_buffer_read_arrayvec() {
+    use arrayvec::ArrayVec;
+
+    let mut buffer = CloneByteBuffer::new3(&[1, 2, 3, 4, 5, 6], 0, 6);
+    let v: ArrayVec<u8, 8> = buffer.read_arrayvec(4);
+    assert_eq!(v.as_slice(), &[1, 2, 3, 4]);
+    assert_eq!(buffer.position(), 4);
+    // capped by the remaining bytes, then by N
+    let v: ArrayVec<u8, 8> = buffer.read_arrayvec(4);
+    assert_eq!(v.as_slice(), &[5, 6]);
+    buffer.rewind();
+    let v: ArrayVec<u8, 2> = buffer.read_arrayvec(4);
+    assert_eq!(v.as_slice(), &[1, 2]);
+    assert_eq!(buffer.position(), 2);
+}
+
+#[test]
+fn test_buffer_skip_find_get_until() {
+    let mut buffer = CloneByteBuffer::new3(b"ab,cd,,e", 0, 8);
+    buffer.skip(1);
+    assert_eq!(buffer.find(b','), Some(2));
+    assert_eq!(buffer.get_until(b','), Some(b"b".to_vec()));
+    assert_eq!(buffer.get_until(b','), Some(b"cd".to_vec()));
+    assert_eq!(buffer.get_until(b','), Some(Vec::new()));
+    assert_eq!(buffer.find(b','), None);
+    assert_eq!(buffer.get_until(b','), None);
+    assert_eq!(buffer.position(), 7);
+}
+
+#[test]
+#[should_panic(expected = "buffer under flow!")]
+fn test_buffer_skip_under_flow() {
+    CloneByteBuffer::new2(4, 4).skip(5);
+}
+
+#[test]
+fn test_buffer_into_iter() {
+    let mut buffer = CloneByteBuffer::new2(8, 8);
+    buffer.put(1);
+    buffer.put(2);
+    buffer.put(3);
+    buffer.flip();
+    buffer.get();
+    let mut v = Vec::new();
+    for b in &buffer {
+        v.push(b);
+    }
+    assert_eq!(v, vec![2, 3]);
+    assert_eq!(buffer.position(), 1);
+    assert_eq!((&buffer).into_iter().len(), 2);
+    assert_eq!(buffer.slice().into_iter().collect::<Vec<u8>>(), vec![2, 3]);
+}
+
+#[test]
+fn test_buffer_read_budget() {
+    use crate::buffer::error::BufferError;
+
+    let mut buffer = CloneByteBuffer::new2(64, 64);
+    buffer.set_read_budget(10);
+    let mut dst = [0u8; 6];
+    assert_eq!(buffer.try_get_buf(&mut dst), Ok(()));
+    assert_eq!(buffer.try_get_i32(), Ok(0));
+    assert_eq!(buffer.read_budget(), Some(0));
+    assert_eq!(buffer.try_get(), Err(BufferError::BudgetExceeded));
+    assert_eq!(buffer.position(), 10);
+
+    // a forged length larger than the budget fails without reading anything
+    let mut big = vec![0u8; 32];
+    buffer.clear();
+    buffer.set_read_budget(16);
+    assert_eq!(buffer.try_get_buf(&mut big), Err(BufferError::BudgetExceeded));
+    assert_eq!(buffer.position(), 0);
+
+    buffer.try_get_i64().unwrap();
+    buffer.clear();
+    assert_eq!(buffer.read_budget(), Some(16));
+}
+
+#[test]
+fn test_buffer_get_all() {
+    let mut buffer = CloneByteBuffer::new3(&[1, 2, 3, 4, 5], 1, 3);
+    assert_eq!(buffer.get_all(), vec![2, 3, 4]);
+    assert!(!buffer.has_remaining());
+    assert_eq!(buffer.position(), 4);
+    assert_eq!(buffer.get_all(), Vec::<u8>::new());
+}
+
+#[test]
+fn test_buffer_arc_threads() {
+    use std::thread;
+
+    let mut buffer = ArcByteBuffer::new2(8, 8);
+    buffer.position_(4);
+    let slice = buffer.slice();
+    buffer.rewind();
+
+    let mut front = buffer.clone();
+    let mut back = slice.clone();
+    let handles = vec![
+        thread::spawn(move || {
+            for i in 0..4 {
+                front.put(i);
+            }
+        }),
+        thread::spawn(move || {
+            for i in 10..14 {
+                back.put(i);
+            }
+        }),
+    ];
+    for h in handles {
+        h.join().unwrap();
+    }
+
+    // both writes are visible through the parent and the slice
+    let mut v = vec![0; 8];
+    buffer.get_buf(&mut v, 0, 8);
+    assert_eq!(v, vec![0, 1, 2, 3, 10, 11, 12, 13]);
+    let mut slice = slice;
+    assert_eq!(slice.get(), 10);
+}
+
+#[test]
+fn test_buffer_undo_redo() {
+    let mut buffer = CloneByteBuffer::new2(8, 8);
+    buffer.put(1);
+    buffer.enable_undo();
+    buffer.put(2);
+    buffer.put_i32(0x0a0b_0c0d);
+    buffer.put_i(9, 0);
+    assert_eq!(*buffer.hb.borrow(), vec![9, 2, 10, 11, 12, 13, 0, 0]);
+
+    assert!(buffer.undo());
+    assert_eq!(*buffer.hb.borrow(), vec![1, 2, 10, 11, 12, 13, 0, 0]);
+    assert!(buffer.undo());
+    assert_eq!(*buffer.hb.borrow(), vec![1, 2, 0, 0, 0, 0, 0, 0]);
+    assert!(buffer.redo());
+    assert_eq!(*buffer.hb.borrow(), vec![1, 2, 10, 11, 12, 13, 0, 0]);
+
+    // a new write drops the redo history
+    buffer.put(7);
+    assert!(!buffer.redo());
+    assert!(buffer.undo());
+    assert!(buffer.undo());
+    assert!(buffer.undo());
+    assert_eq!(*buffer.hb.borrow(), vec![1, 0, 0, 0, 0, 0, 0, 0]);
+    // writes before `enable_undo` are not recorded
+    assert!(!buffer.undo());
+}
+
+#[test]
+fn test_buffer_undo_bulk_writers() {
+    let mut buffer = CloneByteBuffer::new2(8, 8);
+    buffer.enable_undo();
+    buffer.put(1);
+    buffer.put_frame(&[7, 8]);
+    assert_eq!(*buffer.hb.borrow(), vec![1, 0, 0, 0, 2, 7, 8, 0]);
+    // reverts the frame only, not the earlier `put`
+    assert!(buffer.undo());
+    assert_eq!(*buffer.hb.borrow(), vec![1, 0, 0, 0, 0, 0, 0, 0]);
+    assert!(buffer.redo());
+
+    buffer.apply_patch(&[(6, 9), (1, 9)]);
+    buffer.put_iter(std::iter::once(5));
+    buffer.flip();
+    buffer.get();
+    buffer.compact();
+    assert_eq!(*buffer.hb.borrow(), vec![9, 0, 0, 2, 7, 9, 5, 5]);
+    assert!(buffer.undo());
+    assert_eq!(*buffer.hb.borrow(), vec![1, 9, 0, 0, 2, 7, 9, 5]);
+    assert!(buffer.undo());
+    assert_eq!(*buffer.hb.borrow(), vec![1, 9, 0, 0, 2, 7, 9, 0]);
+    // the whole patch is one edit
+    assert!(buffer.undo());
+    assert_eq!(*buffer.hb.borrow(), vec![1, 0, 0, 0, 2, 7, 8, 0]);
+}
+
+#[test]
+fn test_buffer_undo_empty_patch() {
+    let mut buffer = CloneByteBuffer::new2(2, 2);
+    buffer.enable_undo();
+    buffer.put(1);
+    buffer.apply_patch(&[]);
+    // the empty patch is no edit, so `undo` reverts the `put`
+    assert!(buffer.undo());
+    assert_eq!(buffer.get_i(0), 0);
+    assert!(!buffer.undo());
+}
+
+#[test]
+fn test_buffer_undo_limit() {
+    use crate::buffer::clone_bytebuffer::UNDO_LIMIT;
+
+    let mut buffer = CloneByteBuffer::new2(1, 1);
+    buffer.enable_undo();
+    for i in 0..UNDO_LIMIT + 5 {
+        buffer.clear();
+        buffer.put(i as u8);
+    }
+    let mut n = 0;
+    while buffer.undo() {
+        n += 1;
+    }
+    assert_eq!(n, UNDO_LIMIT);
+    assert_eq!(buffer.get_i(0), 4);
+}
+
+#[test]
+fn test_buffer_bytebuffer_get_put() {
+    fn sum<B: IBuffer>(b: &mut B) -> u32 {
+        let mut n = 0;
+        while b.has_remaining() {
+            n += b.get() as u32;
+        }
+        n
+    }
+
+    let mut bb = ByteBuffer::wrap(vec![1, 2, 3, 4]);
+    assert_eq!((bb.position(), bb.limit(), bb.cap()), (0, 4, 4));
+    assert_eq!(bb.get(), 1);
+    bb.put(9);
+    bb.rewind();
+    assert_eq!(sum(&mut bb), 1 + 9 + 3 + 4);
+    assert_eq!(bb.hb, vec![1, 9, 3, 4]);
+}
+
+#[test]
+#[should_panic(expected = "index-only buffer")]
+fn test_buffer_bytebuffer_index_only_get() {
+    ByteBuffer::new_(-1, 0, 4, 4).get();
+}
+
+#[test]
+fn test_buffer_detect_bom() {
+    use crate::buffer::byte_order::ByteOrder;
+
+    let mut buffer = CloneByteBuffer::new3(&[0xfe, 0xff, 0x00, 0x41], 0, 4);
+    buffer.order_(ByteOrder::LittleEndian);
+    assert_eq!(buffer.detect_bom(), Some(ByteOrder::BigEndian));
+    assert_eq!(buffer.order(), ByteOrder::BigEndian);
+    assert_eq!(buffer.get_i16(), 0x41);
+
+    let mut buffer = CloneByteBuffer::new3(&[0xff, 0xfe, 0x41, 0x00], 0, 4);
+    assert_eq!(buffer.detect_bom(), Some(ByteOrder::LittleEndian));
+    assert_eq!(buffer.position(), 2);
+    assert_eq!(buffer.get_i16(), 0x41);
+
+    let mut buffer = CloneByteBuffer::new3(&[0x00, 0x41, 0xfe], 0, 3);
+    assert_eq!(buffer.detect_bom(), None);
+    assert_eq!(buffer.position(), 0);
+    assert_eq!(buffer.order(), ByteOrder::BigEndian);
+    buffer.position_(2);
+    assert_eq!(buffer.detect_bom(), None);
+}
+
+#[test]
+fn test_buffer_allocate() {
+    let buffer = CloneByteBuffer::allocate(16);
+    assert_eq!((buffer.mark(), buffer.position(), buffer.limit(), buffer.cap()), (-1, 0, 16, 16));
+    assert!(!buffer.is_direct());
+    assert_eq!(*buffer.hb.borrow(), vec![0; 16]);
+
+    let mut buffer = CloneByteBuffer::allocate_direct(8);
+    assert_eq!((buffer.mark(), buffer.position(), buffer.limit(), buffer.cap()), (-1, 0, 8, 8));
+    assert!(buffer.is_direct());
+    buffer.put_i32(7);
+    assert!(buffer.slice().is_direct());
+    buffer.flip();
+    assert_eq!(buffer.get_i32(), 7);
+}
+
+#[test]
+fn test_buffer_get_enum() {
+    use crate::buffer::error::BufferError;
+
+    #[derive(Debug, PartialEq)]
+    enum Kind {
+        Ping,
+        Data,
+    }
+    let kind = |b| match b {
+        1 => Some(Kind::Ping),
+        2 => Some(Kind::Data),
+        _ => None,
+    };
+
+    let mut buffer = CloneByteBuffer::new3(&[2, 7], 0, 2);
+    assert_eq!(buffer.get_enum(kind), Ok(Kind::Data));
+    assert_eq!(buffer.position(), 1);
+    assert_eq!(buffer.get_enum(kind), Err(BufferError::InvalidDiscriminant(7)));
+    assert_eq!(buffer.position(), 1);
+    buffer.skip(1);
+    assert_eq!(buffer.get_enum(kind), Err(BufferError::Underflow));
+    assert_eq!(BufferError::InvalidDiscriminant(7).to_string(), "invalid discriminant: 7");
+}
+
+#[test]
+fn test_buffer_string() {
+    use crate::buffer::error::BufferError;
+
+    let mut buffer = CloneByteBuffer::new2(32, 32);
+    buffer.put_string("héllo, 世界").put_string("");
+    assert_eq!(buffer.position(), 4 + 14 + 4);
+    buffer.flip();
+    assert_eq!(&buffer.hb.borrow()[..4], &[0, 0, 0, 14]);
+    assert_eq!(buffer.get_string(), Ok("héllo, 世界".to_string()));
+    assert_eq!(buffer.get_string(), Ok(String::new()));
+    assert_eq!(buffer.get_string(), Err(BufferError::Underflow));
+
+    let mut buffer = CloneByteBuffer::new3(&[0, 0, 0, 2, 0xc3, 0x28, 0, 0, 0, 9, 1], 0, 11);
+    assert!(matches!(buffer.get_string(), Err(BufferError::InvalidUtf8(_))));
+    assert_eq!(buffer.position(), 0);
+    buffer.position_(6);
+    assert_eq!(buffer.get_string(), Err(BufferError::Underflow));
+    assert_eq!(buffer.position(), 6);
+}
+
+#[test]
+#[should_panic(expected = "buffer over flow!")]
+fn test_buffer_put_string_over_flow() {
+    let mut buffer = CloneByteBuffer::new2(6, 6);
+    buffer.put_string("abc");
+}
+
+#[test]
+fn test_buffer_swap() {
+    use crate::buffer::byte_order::ByteOrder;
+
+    let mut front = CloneByteBuffer::new3(&[1, 2, 3], 1, 2);
+    let mut back = CloneByteBuffer::new2(4, 4);
+    back.order_(ByteOrder::LittleEndian);
+    back.put(9);
+    back.mark_();
+    front.swap(&mut back);
+
+    assert_eq!(*front.hb.borrow(), vec![9, 0, 0, 0]);
+    assert_eq!((front.mark(), front.position(), front.limit(), front.cap()), (1, 1, 4, 4));
+    assert_eq!(front.order(), ByteOrder::LittleEndian);
+    assert_eq!(*back.hb.borrow(), vec![1, 2, 3]);
+    assert_eq!((back.mark(), back.position(), back.limit(), back.cap()), (-1, 1, 3, 3));
+    assert_eq!(back.order(), ByteOrder::BigEndian);
+}
+
+#[test]
+fn test_buffer_into_ffi() {
+    let mut buffer = CloneByteBuffer::new2(16, 16);
+    buffer.put_i16(0x0102).put_string("hi");
+    buffer.flip();
+    let bb = buffer.into_ffi();
+    assert_eq!(bb.as_slice(), &[1, 2, 0, 0, 0, 2, b'h', b'i']);
+    bb.destroy();
+
+    // a slice part-way through the parent, past its own position
+    let mut parent = CloneByteBuffer::new3(&[0, 1, 2, 3, 4, 5], 1, 4);
+    let mut slice = parent.slice();
+    slice.get();
+    let bb = slice.into_ffi();
+    assert_eq!(bb.as_slice(), &[2, 3, 4]);
+    bb.destroy();
+    assert_eq!(parent.get(), 1);
+}
+
+#[test]
+#[should_panic(expected = "index out of bounds: tried to access 1 byte at index 8 but limit is 8")]
+fn test_buffer_check_index_message() {
+    let mut buffer = CloneByteBuffer::new2(10, 8);
+    buffer.get_i(8);
+}
+
+#[test]
+#[should_panic(expected = "index out of bounds: tried to access 4 bytes at index 8 but limit is 10")]
+fn test_buffer_check_index_nb_message() {
+    let mut buffer = Buffer::new_(-1, 0, 10, 10);
+    assert_eq!(buffer.check_index_nb(6, 4), 6);
+    buffer.check_index_nb(8, 4);
+}
+
+#[test]
+#[should_panic(expected = "index out of bounds: tried to access 6 elements at offset 2 but size is 5")]
+fn test_buffer_check_bounds_message() {
+    let mut buffer = CloneByteBuffer::new2(10, 10);
+    let mut dst = vec![0; 5];
+    buffer.get_buf(&mut dst, 2, 6);
+}
+
+#[test]
+fn test_buffer_from_ffi() {
+    let bb = crate::bytebuffer::ByteBuffer::from_vec(vec![0, 7, 1, 2, 3]);
+    let ptr = bb.as_slice().as_ptr();
+    let mut buffer = CloneByteBuffer::from_ffi(bb);
+    assert_eq!((buffer.position(), buffer.limit(), buffer.cap()), (0, 5, 5));
+    // the allocation moved over as is
+    assert_eq!(buffer.hb.borrow().as_ptr(), ptr);
+    assert_eq!(buffer.get_i16(), 7);
+
+    // and back again
+    let bb = buffer.into_ffi();
+    assert_eq!(bb.as_slice(), &[1, 2, 3]);
+    let back = CloneByteBuffer::from_ffi(bb);
+    assert_eq!(*back.hb.borrow(), vec![1, 2, 3]);
+
+    let empty = CloneByteBuffer::from_ffi(crate::bytebuffer::ByteBuffer::default());
+    assert_eq!(empty.cap(), 0);
+}
+
+#[test]
+fn test_buffer_put_slice() {
+    const HEADER: &[u8] = b"\x89PNG\r\n";
+    let mut buffer = CloneByteBuffer::new2(8, 8);
+    buffer.put_slice(HEADER, 1, 3).put_slice("ok".as_bytes(), 0, 2);
+    assert_eq!(buffer.position(), 5);
+    assert_eq!(&buffer.hb.borrow()[..5], b"PNGok");
+}
+
+#[test]
+#[should_panic(expected = "buffer over flow!")]
+fn test_buffer_put_slice_over_flow() {
+    let mut buffer = CloneByteBuffer::new2(2, 2);
+    buffer.put_slice(&[1, 2, 3], 0, 3);
+}
+
+#[test]
+fn test_buffer_cursor_put_default() {
+    use crate::buffer::error::BufferError;
+    let mut cursor = Buffer::new_(-1, 2, 2, 2);
+    assert_eq!(cursor.try_put(1), Err(BufferError::Overflow));
+    cursor.position_(0);
+    let err = std::panic::catch_unwind(move || cursor.put(1)).unwrap_err();
+    assert_eq!(err.downcast_ref::<&str>(), Some(&"put is not supported by this buffer!"));
+}
+
+#[test]
+fn test_buffer_put_buffer_separate() {
+    let mut src = CloneByteBuffer::new3(&[1, 2, 3, 4], 1, 3);
+    let mut dst = CloneByteBuffer::new2(4, 4);
+    dst.position_(1);
+    dst.put_buffer(&mut src);
+    assert_eq!(*dst.hb.borrow(), vec![0, 2, 3, 4]);
+    assert_eq!(dst.position(), 4);
+    assert!(!src.has_remaining());
+}
+
+#[test]
+fn test_buffer_read_only_flag() {
+    let buffer = CloneByteBuffer::new3(&[1, 2, 3], 0, 3);
+    assert!(!buffer.is_read_only());
+    let ro = buffer.as_read_only();
+    assert!(ro.is_read_only());
+    assert!(ro.slice().is_read_only());
+    assert!(ro.duplicate().is_read_only());
+    assert!(!buffer.is_read_only());
+
+    // header round trip keeps the flag, and the restored buffer refuses writes
+    let mut back = CloneByteBuffer::from_ffi_with_header(ro.to_ffi_with_header()).unwrap();
+    assert!(back.is_read_only());
+    let err = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| back.put(7))).unwrap_err();
+    assert_eq!(err.downcast_ref::<&str>(), Some(&"read only buffer!"));
+}
+
+#[test]
+fn test_buffer_read_only_single_byte() {
+    use crate::tests::testutil::assert_read_only_write;
+
+    assert_read_only_write("put", |b| b.put(9));
+    assert_read_only_write("put_i", |b| b.put_i(9, 0));
+    assert_read_only_write("try_put", |b| {
+        let _ = IBuffer::try_put(b, 9);
+    });
+}
+
+#[test]
+fn test_buffer_read_only_bulk() {
+    use crate::tests::testutil::assert_read_only_write;
+
+    assert_read_only_write("put_buf", |b| {
+        b.put_buf(&mut vec![9, 9], 0, 2);
+    });
+    assert_read_only_write("put_slice", |b| {
+        b.put_slice(&[9, 9], 0, 2);
+    });
+    assert_read_only_write("put_buffer", |b| b.put_buffer(&mut CloneByteBuffer::new3(&[9], 0, 1)));
+    assert_read_only_write("put_iter", |b| {
+        b.put_iter(std::iter::repeat(9));
+    });
+    assert_read_only_write("fill_from", |b| {
+        let _ = b.fill_from(&mut &[9u8, 9][..]);
+    });
+    assert_read_only_write("append_buffer", |b| {
+        b.append_buffer(&CloneByteBuffer::new3(&[9], 0, 1));
+    });
+}
+
+#[test]
+fn test_buffer_read_only_typed() {
+    use crate::tests::testutil::assert_read_only_write;
+
+    assert_read_only_write("put_i16", |b| {
+        b.put_i16(9);
+    });
+    assert_read_only_write("put_i32_with", |b| {
+        b.put_i32_with(9, crate::buffer::byte_order::ByteOrder::LittleEndian);
+    });
+    assert_read_only_write("put_u128", |b| {
+        b.put_u128(9);
+    });
+    assert_read_only_write("try_put_i32", |b| {
+        let _ = b.try_put_i32(9);
+    });
+    assert_read_only_write("put_uint_checked", |b| {
+        let _ = b.put_uint_checked(9, 2);
+    });
+    assert_read_only_write("put_varint", |b| {
+        b.put_varint(300);
+    });
+    assert_read_only_write("put_string", |b| {
+        b.put_string("");
+    });
+    assert_read_only_write("put_cstr", |b| b.put_cstr("a"));
+    assert_read_only_write("put_frame", |b| b.put_frame(&[9]));
+}
+
+#[test]
+fn test_buffer_read_only_in_place() {
+    use crate::tests::testutil::assert_read_only_write;
+
+    assert_read_only_write("swap_bytes_u16", |b| {
+        b.swap_bytes_u16();
+    });
+    assert_read_only_write("compact", |b| {
+        b.compact();
+    });
+    assert_read_only_write("discard_consumed", |b| {
+        b.discard_consumed();
+    });
+    assert_read_only_write("pad_start", |b| b.pad_start(6, 0));
+    assert_read_only_write("apply_patch", |b| {
+        b.apply_patch(&[(0, 9)]);
+    });
+    assert_read_only_write("as_mut_ptr", |b| {
+        b.as_mut_ptr();
+    });
+}
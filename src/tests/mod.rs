@@ -1,2 +1,6 @@
+#[cfg(test)]
+mod bits_test;
 mod buffer_test;
-mod slice_test;
\ No newline at end of file
+mod slice_test;
+#[cfg(test)]
+pub(crate) mod testutil;
\ No newline at end of file
@@ -0,0 +1,34 @@
+use std::fmt::Debug;
+use std::mem::size_of;
+use std::panic::{catch_unwind, AssertUnwindSafe};
+
+use crate::buffer::buffer::IBuffer;
+use crate::buffer::clone_bytebuffer::CloneByteBuffer;
+
+/// Write `value` with `put`, flip, read it back with `get` and check that it
+/// survived the trip and that both sides moved the position by `size_of::<T>()`.
+pub fn assert_round_trip<T, P, G>(value: T, put: P, get: G)
+where
+    T: Copy + PartialEq + Debug,
+    P: Fn(&mut CloneByteBuffer, T),
+    G: Fn(&mut CloneByteBuffer) -> T,
+{
+    let size = size_of::<T>() as i32;
+    let mut buffer = CloneByteBuffer::new2(size, size);
+    put(&mut buffer, value);
+    assert_eq!(buffer.position(), size, "put moved position by the wrong amount");
+    buffer.flip();
+    assert_eq!(get(&mut buffer), value);
+    assert_eq!(buffer.position(), size, "get moved position by the wrong amount");
+}
+
+/// Run `write` on a read-only `CloneByteBuffer` and check that it panics with
+/// "read only buffer!", leaving the bytes and the position untouched.
+pub fn assert_read_only_write(name: &str, write: impl FnOnce(&mut CloneByteBuffer)) {
+    let writable = CloneByteBuffer::new3(&[1, 2, 3, 4, 5, 6, 7, 8], 2, 4);
+    let mut ro = writable.as_read_only();
+    let err = catch_unwind(AssertUnwindSafe(|| write(&mut ro))).expect_err(name);
+    assert_eq!(err.downcast_ref::<&str>(), Some(&"read only buffer!"), "{}", name);
+    assert_eq!(*ro.hb.borrow(), vec![1, 2, 3, 4, 5, 6, 7, 8], "{}", name);
+    assert_eq!((ro.position(), ro.limit(), ro.cap()), (2, 6, 8), "{}", name);
+}